@@ -0,0 +1,255 @@
+//! Plays every level against its saved solution without a window and reports which ones are won.
+//! A level without a solution fails the run, every shipped level has to be shown to be winnable.
+//!
+//! Solutions are ghost hand recordings in `assets/solutions/<level id>/*.recording.toml`.
+//! Record them in game, press F5 to export them to `assets/recordings/<level id>`, then copy
+//! the ones that solve the level over.
+//!
+//! `cargo run --bin verify [--ticks N] [level id...]`
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::input::InputPlugin;
+use bevy::state::app::StatesPlugin;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::time::TimeUpdateStrategy;
+use manos::hand::{ImportRecording, Playback};
+use manos::level::{LevelCompleted, LevelManifest, LoadLevel};
+use manos::object::{Hitbox, Object};
+use manos::state::GameState;
+use manos::submit::LevelTime;
+
+// matches the default fixed timestep so every update runs exactly one FixedUpdate
+const TICK: Duration = Duration::from_micros(15625);
+const DEFAULT_TICKS: u32 = 64 * 60;
+// how many updates to wait for assets before giving up
+const LOAD_TIMEOUT: u32 = 600;
+
+#[derive(Resource, Default)]
+struct Won(Option<Duration>);
+
+enum Outcome {
+    Solved(Duration),
+    Unsolved,
+    NoSolution,
+    LoadFailed(&'static str),
+}
+
+fn main() -> ExitCode {
+    let mut ticks = DEFAULT_TICKS;
+    let mut only = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--ticks" {
+            match args.next().and_then(|ticks| ticks.parse().ok()) {
+                Some(value) => ticks = value,
+                None => {
+                    eprintln!("--ticks needs a number");
+                    return ExitCode::FAILURE;
+                }
+            }
+        } else {
+            only.push(arg);
+        }
+    }
+
+    let manifest = match std::fs::read_to_string(assets_dir().join("levels/campaign.manifest.toml"))
+        .map_err(|e| e.to_string())
+        .and_then(|text| toml::from_str::<LevelManifest>(&text).map_err(|e| e.to_string()))
+    {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            eprintln!("could not read levels/campaign.manifest.toml: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut failed = false;
+
+    for level in manifest.levels.iter().filter(|level| only.is_empty() || only.contains(&level.id)) {
+        let outcome = verify_level(&level.id, ticks);
+
+        match outcome {
+            Outcome::Solved(time) => println!("{:<20} solved in {:.2}s", level.id, time.as_secs_f32()),
+            Outcome::Unsolved => println!("{:<20} FAILED, not won after {} ticks", level.id, ticks),
+            Outcome::NoSolution => println!("{:<20} FAILED, no solution in assets/solutions/{}", level.id, level.id),
+            Outcome::LoadFailed(what) => println!("{:<20} FAILED, {} did not load", level.id, what),
+        }
+
+        failed |= !matches!(outcome, Outcome::Solved(_));
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn assets_dir() -> PathBuf {
+    let root = std::env::var("BEVY_ASSET_ROOT")
+        .or_else(|_| std::env::var("CARGO_MANIFEST_DIR"))
+        .unwrap_or_else(|_| ".".into());
+
+    PathBuf::from(root).join("assets")
+}
+
+fn solutions(level: &str) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(assets_dir().join("solutions").join(level)) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<String> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.ends_with(".recording.toml"))
+        .map(|name| format!("solutions/{}/{}", level, name))
+        .collect();
+
+    files.sort();
+    files
+}
+
+fn verify_level(level: &str, ticks: u32) -> Outcome {
+    let recordings = solutions(level);
+
+    if recordings.is_empty() {
+        return Outcome::NoSolution;
+    }
+
+    let mut app = headless_app();
+
+    // skip the menus
+    app.world_mut().resource_mut::<NextState<GameState>>().set(GameState::Playing);
+
+    if !update_until(&mut app, |world| !world.resource::<Assets<LevelManifest>>().is_empty()) {
+        return Outcome::LoadFailed("the level manifest");
+    }
+
+    app.world_mut().send_event(LoadLevel(level.to_string()));
+
+    if !update_until(&mut app, |world| world.query_filtered::<(), With<Object>>().iter(world).next().is_some()) {
+        return Outcome::LoadFailed("the level");
+    }
+
+    let count = recordings.len();
+    for recording in recordings {
+        app.world_mut().send_event(ImportRecording(recording));
+    }
+
+    if !update_until(&mut app, |world| world.query_filtered::<(), With<Playback>>().iter(world).count() == count) {
+        return Outcome::LoadFailed("a solution recording");
+    }
+
+    for _ in 0..ticks {
+        app.update();
+
+        if let Some(time) = app.world().resource::<Won>().0 {
+            return Outcome::Solved(time);
+        }
+    }
+
+    Outcome::Unsolved
+}
+
+fn headless_app() -> App {
+    let mut app = App::new();
+
+    app
+        .add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            TransformPlugin,
+            HierarchyPlugin,
+            InputPlugin,
+            StatesPlugin,
+        ))
+        // without a renderer nothing loads these, the game only needs the handles and image sizes
+        .init_asset::<Image>()
+        .init_asset::<Mesh>()
+        .init_asset::<ColorMaterial>()
+        // lets the HUD's gizmos run, they are never drawn
+        .init_gizmo_group::<DefaultGizmoConfigGroup>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(TICK))
+        .init_resource::<Won>();
+
+    manos::register(&mut app);
+
+    app.add_systems(Update, (image_sizes, watch_for_win));
+
+    app.finish();
+    app.cleanup();
+
+    app
+}
+
+fn update_until(app: &mut App, mut done: impl FnMut(&mut World) -> bool) -> bool {
+    for _ in 0..LOAD_TIMEOUT {
+        app.update();
+
+        if done(app.world_mut()) {
+            return true;
+        }
+    }
+
+    false
+}
+
+fn watch_for_win(
+    mut ev_completed: EventReader<LevelCompleted>,
+    mut won: ResMut<Won>,
+    level_time: Res<LevelTime>,
+) {
+    if ev_completed.read().count() > 0 && won.0.is_none() {
+        won.0 = Some(level_time.0.elapsed());
+    }
+}
+
+// Colliders are sized from their sprite, so give every sprite a blank image of the right size
+// read straight from the PNG header.
+fn image_sizes(
+    sprites: Query<&Handle<Image>, (With<Object>, Without<Hitbox>)>,
+    mut images: ResMut<Assets<Image>>,
+    asset_server: Res<AssetServer>,
+) {
+    for handle in sprites.iter() {
+        if images.contains(handle) {
+            continue;
+        }
+
+        let Some(path) = asset_server.get_path(handle.id()) else {
+            continue;
+        };
+
+        let Some((width, height)) = std::fs::read(assets_dir().join(path.path()))
+            .ok()
+            .and_then(|bytes| png_size(&bytes))
+        else {
+            continue;
+        };
+
+        let image = Image::new_fill(
+            Extent3d { width, height, depth_or_array_layers: 1 },
+            TextureDimension::D2,
+            &[0, 0, 0, 0],
+            TextureFormat::Rgba8Unorm,
+            RenderAssetUsages::MAIN_WORLD,
+        );
+
+        images.insert(handle.id(), image);
+    }
+}
+
+fn png_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    // 8 byte signature, then the IHDR chunk's length and name, then width and height
+    let header = bytes.get(16..24)?;
+
+    Some((
+        u32::from_be_bytes(header[0..4].try_into().ok()?),
+        u32::from_be_bytes(header[4..8].try_into().ok()?),
+    ))
+}
@@ -8,3 +8,16 @@ pub(super) fn register(app: &mut App) {
 fn setup(mut commands: Commands) {
     commands.spawn(Camera2dBundle::default());
 }
+
+/// Where the cursor is in the world, `None` when it's outside the window or there is no window.
+pub fn cursor_world_position(
+    windows: &Query<&Window>,
+    cameras: &Query<(&Camera, &GlobalTransform)>,
+) -> Option<Vec2> {
+    let window = windows.get_single().ok()?;
+    let (camera, camera_transform) = cameras.get_single().ok()?;
+
+    window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+}
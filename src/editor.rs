@@ -0,0 +1,492 @@
+use avian2d::prelude::*;
+use bevy::input::mouse::MouseWheel;
+use camera::cursor_world_position;
+use hand::Grabbing;
+use level::{spawn_object, ActiveLevel, CurrentLevel, EditorSave, Level, LevelManifest, LevelObject, LoadObject, ManifestHandle};
+use object::{Collector, CollectorInteraction, GrabInteractions, Grabbed, Tags};
+use state::GameState;
+
+use super::*;
+
+/// Lets you place, move, scale and delete level objects with the mouse and save them back to the level file.
+///
+/// Tab toggles the editor, physics and hands are paused while it is open.
+#[derive(Resource, Default)]
+pub struct Editor {
+    pub enabled: bool,
+    selected: Option<Entity>,
+    // offset from the cursor to the selected object while it is dragged
+    drag: Option<Vec2>,
+}
+
+/// Run condition for systems that should stop while the editor is open.
+pub fn editing(editor: Res<Editor>) -> bool {
+    editor.enabled
+}
+
+#[derive(Component)]
+struct EditorPanel;
+
+const PICK_RADIUS: f32 = 16.0;
+
+pub(super) fn register(app: &mut App) {
+    app
+        .init_resource::<Editor>()
+        .add_systems(Update, toggle_editor.run_if(in_state(GameState::Playing)))
+        .add_systems(OnExit(GameState::Playing), close_editor)
+        .add_systems(Update, (
+            select_object,
+            drag_object,
+            scale_object,
+            edit_object,
+            save_level,
+            update_panel,
+            draw_selection,
+        ).chain().run_if(editing));
+}
+
+fn toggle_editor(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut editor: ResMut<Editor>,
+    mut physics_time: ResMut<Time<Physics>>,
+    panels: Query<Entity, With<EditorPanel>>,
+) {
+    if !keys.just_pressed(KeyCode::Tab) {
+        return;
+    }
+
+    editor.enabled = !editor.enabled;
+    editor.selected = None;
+    editor.drag = None;
+
+    if editor.enabled {
+        physics_time.pause();
+
+        commands.spawn((
+            TextBundle::from_section("", TextStyle {
+                font_size: 18.0,
+                color: Color::WHITE,
+                ..default()
+            })
+            .with_background_color(Color::srgba(0.0, 0.0, 0.0, 0.7))
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                right: Val::Px(10.0),
+                width: Val::Px(320.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                ..default()
+            }),
+            EditorPanel,
+        ));
+    } else {
+        physics_time.unpause();
+
+        for panel in panels.iter() {
+            commands.entity(panel).despawn();
+        }
+    }
+}
+
+fn close_editor(
+    mut commands: Commands,
+    mut editor: ResMut<Editor>,
+    panels: Query<Entity, With<EditorPanel>>,
+) {
+    *editor = Editor::default();
+
+    for panel in panels.iter() {
+        commands.entity(panel).despawn();
+    }
+}
+
+fn distance_to_segment(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let segment = end - start;
+    let t = ((point - start).dot(segment) / segment.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+
+    point.distance(start + segment * t)
+}
+
+fn select_object(
+    mouse: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    objects: Query<(Entity, &Transform, &LevelObject, Option<&Handle<Image>>)>,
+    images: Res<Assets<Image>>,
+    mut editor: ResMut<Editor>,
+) {
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let Some(cursor) = cursor_world_position(&windows, &cameras) else {
+        return;
+    };
+
+    let mut picked: Option<(Entity, &Transform)> = None;
+
+    for (entity, transform, level_object, image) in objects.iter() {
+        let position = transform.translation.truncate();
+        let source = &level_object.source;
+
+        let hit = match (image.and_then(|image| images.get(image)), &source.collider_info) {
+            (Some(image), _) => {
+                let half_size = image.size().as_vec2() * transform.scale.truncate() / 2.0;
+                let offset = (cursor - position).abs();
+
+                offset.x <= half_size.x && offset.y <= half_size.y
+            }
+            (None, Some(collider_info)) if collider_info.name == "segment" => {
+                let size = collider_info.size.clone().unwrap_or_default();
+                let end = position + Vec2::new(size[0], size[1]);
+
+                distance_to_segment(cursor, position, end) <= PICK_RADIUS
+            }
+            _ => cursor.distance(position) <= PICK_RADIUS,
+        };
+
+        // pick the top most object
+        if hit && picked.map_or(true, |(_, picked)| transform.translation.z > picked.translation.z) {
+            picked = Some((entity, transform));
+        }
+    }
+
+    editor.selected = picked.map(|(entity, _)| entity);
+    editor.drag = picked.map(|(_, transform)| transform.translation.truncate() - cursor);
+}
+
+fn drag_object(
+    mouse: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut objects: Query<(&mut Transform, &mut LevelObject)>,
+    mut editor: ResMut<Editor>,
+) {
+    if !mouse.pressed(MouseButton::Left) {
+        editor.drag = None;
+        return;
+    }
+
+    let (Some(selected), Some(offset)) = (editor.selected, editor.drag) else {
+        return;
+    };
+
+    let (Some(cursor), Ok((mut transform, mut level_object))) = (
+        cursor_world_position(&windows, &cameras),
+        objects.get_mut(selected),
+    ) else {
+        return;
+    };
+
+    let position = (cursor + offset).round();
+
+    transform.translation.x = position.x;
+    transform.translation.y = position.y;
+    level_object.source.position[0] = position.x;
+    level_object.source.position[1] = position.y;
+}
+
+fn scale_object(
+    mut ev_wheel: EventReader<MouseWheel>,
+    mut objects: Query<(&mut Transform, &mut LevelObject)>,
+    editor: Res<Editor>,
+) {
+    let scroll: f32 = ev_wheel.read().map(|ev| ev.y.signum()).sum();
+
+    if scroll == 0.0 {
+        return;
+    }
+
+    let Some((mut transform, mut level_object)) = editor.selected.and_then(|selected| objects.get_mut(selected).ok()) else {
+        return;
+    };
+
+    let factor = 1.1_f32.powf(scroll);
+
+    transform.scale.x *= factor;
+    transform.scale.y *= factor;
+    level_object.source.scale = vec![transform.scale.x, transform.scale.y];
+}
+
+fn toggle(value: &mut Option<bool>) {
+    *value = if *value == Some(true) { None } else { Some(true) };
+}
+
+fn edit_object(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    objects: Query<&LevelObject>,
+    grabbed: Query<&Grabbed>,
+    grabbing: Query<&Grabbing>,
+    asset_server: Res<AssetServer>,
+    mut editor: ResMut<Editor>,
+) {
+    let selected = editor.selected.and_then(|selected| Some((selected, objects.get(selected).ok()?)));
+
+    // place a copy of the selected object, or a new one, under the cursor
+    if keys.just_pressed(KeyCode::KeyN) {
+        let Some(cursor) = cursor_world_position(&windows, &cameras) else {
+            return;
+        };
+
+        let mut source = selected.map_or_else(
+            || LoadObject {
+                texture_name: Some("ducky.png".into()),
+                item: Some("ducky".into()),
+                position: vec![0.0, 0.0, 0.0],
+                scale: vec![1.0, 1.0],
+                grabbable: Some(true),
+                ..default()
+            },
+            |(_, level_object)| level_object.source.clone(),
+        );
+
+        source.position[0] = cursor.x.round();
+        source.position[1] = cursor.y.round();
+
+        let index = objects.iter().map(|level_object| level_object.index + 1).max().unwrap_or(0);
+
+        editor.selected = Some(spawn_object(&mut commands, &asset_server, index, source));
+        return;
+    }
+
+    let Some((entity, level_object)) = selected else {
+        return;
+    };
+
+    if keys.any_just_pressed([KeyCode::Delete, KeyCode::Backspace]) {
+        release(&mut commands, entity, &grabbed, &grabbing);
+        commands.entity(entity).despawn_recursive();
+        editor.selected = None;
+        return;
+    }
+
+    let mut source = level_object.source.clone();
+
+    // item kinds that are used in the level, for collectors to collect
+    let mut kinds: Vec<String> = objects.iter()
+        .filter_map(|level_object| level_object.source.item.clone())
+        .collect();
+    kinds.sort();
+    kinds.dedup();
+
+    if keys.just_pressed(KeyCode::Digit1) {
+        toggle(&mut source.grabbable);
+    } else if keys.just_pressed(KeyCode::Digit2) {
+        source.grab = match source.grab {
+            Some(GrabInteractions::Spawn) => None,
+            _ => Some(GrabInteractions::Spawn),
+        };
+    } else if keys.just_pressed(KeyCode::Digit3) {
+        toggle(&mut source.sensor);
+    } else if keys.just_pressed(KeyCode::Digit4) {
+        toggle(&mut source.anchored);
+    } else if keys.just_pressed(KeyCode::Digit5) {
+        toggle(&mut source.body_static);
+    } else if keys.just_pressed(KeyCode::Digit6) {
+        source.collider_info = match source.collider_info.as_ref().map(|info| info.name.as_str()) {
+            Some("segment") => source.collider_info.clone(),
+            Some("circle") => None,
+            _ => Some(level::ColliderInfo {
+                name: "circle".into(),
+                size: None,
+            }),
+        };
+    } else if keys.just_pressed(KeyCode::Digit7) {
+        source.collector = match &source.collector {
+            None => Some(Collector {
+                collecting: Tags::One(kinds.first().cloned().unwrap_or_default()),
+                interaction: CollectorInteraction::Count,
+                orientation: None,
+            }),
+            Some(Collector { interaction: CollectorInteraction::Count, collecting, orientation }) => Some(Collector {
+                collecting: collecting.clone(),
+                interaction: CollectorInteraction::FinishLevel,
+                orientation: *orientation,
+            }),
+            Some(_) => None,
+        };
+    } else if keys.just_pressed(KeyCode::Digit8) {
+        if let Some(collector) = &mut source.collector {
+            // lists written by hand are replaced by a single kind
+            let current = match &collector.collecting {
+                Tags::One(kind) => kinds.iter().position(|other| other == kind),
+                Tags::Many(_) => None,
+            };
+
+            if let Some(kind) = kinds.get(current.map_or(0, |index| index + 1)).or(kinds.first()) {
+                collector.collecting = Tags::One(kind.clone());
+            }
+        }
+    } else if keys.just_pressed(KeyCode::Digit9) {
+        if let Some(collector) = &mut source.collector {
+            // any way round, then each quarter turn
+            collector.orientation = match collector.orientation {
+                None => Some(0.0),
+                Some(degrees) if degrees < 270.0 => Some((degrees / 90.0).floor() * 90.0 + 90.0),
+                Some(_) => None,
+            };
+        }
+    } else {
+        return;
+    }
+
+    // the object's components all come from its source, so rebuild it
+    release(&mut commands, entity, &grabbed, &grabbing);
+    commands.entity(entity).despawn_recursive();
+    editor.selected = Some(spawn_object(&mut commands, &asset_server, level_object.index, source));
+}
+
+// Lets go of `object` if a hand is holding it, the hand's joint would point at nothing once it's
+// despawned.
+fn release(commands: &mut Commands, object: Entity, grabbed: &Query<&Grabbed>, grabbing: &Query<&Grabbing>) {
+    let Ok(Grabbed(hand)) = grabbed.get(object) else {
+        return;
+    };
+
+    if let Ok(Grabbing(joint)) = grabbing.get(*hand) {
+        commands.entity(*joint).despawn();
+    }
+
+    commands.entity(*hand).remove::<Grabbing>();
+}
+
+fn save_level(
+    keys: Res<ButtonInput<KeyCode>>,
+    objects: Query<&LevelObject>,
+    current_level: Res<CurrentLevel>,
+    active_level: Option<Res<ActiveLevel>>,
+    manifest: Res<ManifestHandle>,
+    manifests: Res<Assets<LevelManifest>>,
+    mut levels: ResMut<Assets<Level>>,
+    mut editor_save: ResMut<EditorSave>,
+) {
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+
+    if !(ctrl && keys.just_pressed(KeyCode::KeyS)) {
+        return;
+    }
+
+    let (Some(id), Some(active_level), Some(manifest)) = (&current_level.0, active_level, manifests.get(&manifest.0)) else {
+        return;
+    };
+
+    let (Some(entry), Some(level)) = (manifest.get(id), levels.get(&active_level.0)) else {
+        return;
+    };
+
+    let mut sources: Vec<&LevelObject> = objects.iter().collect();
+    sources.sort_by_key(|level_object| level_object.index);
+
+    let edited = Level {
+        background_color: level.background_color.clone(),
+        goal: level.goal.clone(),
+        loop_length: level.loop_length,
+        max_ghosts: level.max_ghosts,
+        objects: sources.into_iter().map(|level_object| level_object.source.clone()).collect(),
+    };
+
+    if let Err(e) = edited.validate(&entry.file) {
+        error!("not saving, {}", e);
+        return;
+    }
+
+    let text = match toml::to_string_pretty(&edited) {
+        Ok(text) => text,
+        Err(e) => {
+            error!("could not write {}: {}", entry.file, e);
+            return;
+        }
+    };
+
+    write_level(&entry.file, &text);
+
+    // keep the loaded asset in step so reloading the level doesn't bring back the old version,
+    // without hot reload rebuilding the level the editor is working on
+    editor_save.0 = Some(text);
+    levels.insert(active_level.0.id(), edited);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_level(file: &str, text: &str) {
+    use bevy::asset::io::file::FileAssetReader;
+
+    let path = FileAssetReader::get_base_path().join("assets").join(file);
+
+    match std::fs::write(&path, text) {
+        Ok(()) => info!("saved level to {}", path.display()),
+        Err(e) => error!("could not save level to {}: {}", path.display(), e),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_level(_file: &str, _text: &str) {
+    warn!("saving levels isn't supported on the web");
+}
+
+fn update_panel(
+    mut panels: Query<&mut Text, With<EditorPanel>>,
+    objects: Query<&LevelObject>,
+    editor: Res<Editor>,
+) {
+    let Ok(mut text) = panels.get_single_mut() else {
+        return;
+    };
+
+    let mut lines = vec![
+        "LEVEL EDITOR (Tab to leave)".to_string(),
+        "click select, drag move, wheel scale".to_string(),
+        "N place, Del delete, Ctrl+S save".to_string(),
+        String::new(),
+    ];
+
+    match editor.selected.and_then(|selected| objects.get(selected).ok()) {
+        Some(level_object) => {
+            let source = &level_object.source;
+            let flag = |value: Option<bool>| if value == Some(true) { "yes" } else { "no" };
+
+            lines.push(format!("objects[{}] {}", level_object.index, source.texture_name.as_deref().unwrap_or("(no texture)")));
+            lines.push(format!("item: {}", source.item.as_deref().unwrap_or("none")));
+            lines.push(format!("position {:?}", source.position));
+            lines.push(format!("scale {:?}", source.scale));
+            lines.push(format!("1 grabbable: {}", flag(source.grabbable)));
+            lines.push(format!("2 grab: {:?}", source.grab.clone().unwrap_or(GrabInteractions::Grab)));
+            lines.push(format!("3 sensor: {}", flag(source.sensor)));
+            lines.push(format!("4 anchored: {}", flag(source.anchored)));
+            lines.push(format!("5 static: {}", flag(source.body_static)));
+            lines.push(format!("6 collider: {}", source.collider_info.as_ref().map_or("rectangle", |info| info.name.as_str())));
+
+            match &source.collector {
+                Some(collector) => {
+                    lines.push(format!("7 collector: {:?}", collector.interaction));
+                    lines.push(format!("8 collecting: {}", collector.collecting.iter().cloned().collect::<Vec<_>>().join(", ")));
+                    lines.push(match collector.orientation {
+                        Some(degrees) => format!("9 orientation: {}°", degrees),
+                        None => "9 orientation: any".to_string(),
+                    });
+                }
+                None => lines.push("7 collector: none".to_string()),
+            }
+        }
+        None => lines.push("nothing selected".to_string()),
+    }
+
+    text.sections[0].value = lines.join("\n");
+}
+
+fn draw_selection(
+    mut gizmos: Gizmos,
+    objects: Query<&Transform, With<LevelObject>>,
+    editor: Res<Editor>,
+) {
+    for transform in objects.iter() {
+        gizmos.circle_2d(transform.translation.truncate(), 4.0, Color::srgb(0.3, 0.3, 0.3));
+    }
+
+    if let Some(transform) = editor.selected.and_then(|selected| objects.get(selected).ok()) {
+        gizmos.circle_2d(transform.translation.truncate(), PICK_RADIUS, Color::srgb(1.0, 0.8, 0.0));
+    }
+}
@@ -0,0 +1,234 @@
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+// the move action isn't remappable, these keys always steer the hand
+const MOVE_KEYS: [KeyCode; 8] = [
+    KeyCode::KeyW, KeyCode::KeyA, KeyCode::KeyS, KeyCode::KeyD,
+    KeyCode::ArrowUp, KeyCode::ArrowLeft, KeyCode::ArrowDown, KeyCode::ArrowRight,
+];
+
+/// One button bound to an action, `{ key = "KeyE" }`, `{ mouse = "Left" }` or `{ gamepad = "South" }`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
+}
+
+impl Binding {
+    /// Whether both are keys, both mouse buttons or both gamepad buttons.
+    pub fn same_kind(&self, other: &Binding) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+impl std::fmt::Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{:?}", key),
+            Binding::Mouse(button) => write!(f, "Mouse {:?}", button),
+            Binding::Gamepad(button) => write!(f, "Pad {:?}", button),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ActionBindings {
+    pub action: HandActions,
+    pub inputs: Vec<Binding>,
+}
+
+/// The buttons bound to every remappable hand action, saved to `bindings.toml` in the config
+/// directory and used to build the hand's input map.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+pub struct Bindings {
+    pub actions: Vec<ActionBindings>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Binding::*;
+
+        let actions = [
+            (HandActions::Grab, vec![Mouse(MouseButton::Left), Key(KeyCode::KeyE), Gamepad(GamepadButtonType::South)]),
+            (HandActions::RotateLeft, vec![Key(KeyCode::KeyZ), Gamepad(GamepadButtonType::LeftTrigger)]),
+            (HandActions::RotateRight, vec![Key(KeyCode::KeyX), Gamepad(GamepadButtonType::RightTrigger)]),
+            (HandActions::Record, vec![Mouse(MouseButton::Right), Key(KeyCode::Space), Gamepad(GamepadButtonType::West)]),
+            (HandActions::Reload, vec![Key(KeyCode::KeyR), Gamepad(GamepadButtonType::Select)]),
+            (HandActions::Export, vec![Key(KeyCode::F5)]),
+            (HandActions::Import, vec![Key(KeyCode::F9)]),
+            (HandActions::CycleLoop, vec![Key(KeyCode::KeyL), Gamepad(GamepadButtonType::North)]),
+            (HandActions::WaitForLoop, vec![Key(KeyCode::KeyK), Gamepad(GamepadButtonType::East)]),
+            (HandActions::SelectGhost, vec![Mouse(MouseButton::Middle)]),
+            (HandActions::DeleteGhost, vec![Key(KeyCode::Delete)]),
+            (HandActions::MuteGhost, vec![Key(KeyCode::KeyM)]),
+        ];
+
+        Self {
+            actions: actions.into_iter()
+                .map(|(action, inputs)| ActionBindings { action, inputs })
+                .collect(),
+        }
+    }
+}
+
+impl Bindings {
+    /// The defaults with every action found in `saved` taken from it, so actions added since
+    /// the file was written still get bound.
+    pub fn merged(saved: Bindings) -> Self {
+        let mut bindings = Self::default();
+
+        for entry in bindings.actions.iter_mut() {
+            if let Some(saved) = saved.actions.iter().find(|saved| saved.action == entry.action) {
+                entry.inputs.clone_from(&saved.inputs);
+            }
+        }
+
+        bindings
+    }
+
+    pub fn get(&self, action: HandActions) -> &[Binding] {
+        self.actions.iter()
+            .find(|entry| entry.action == action)
+            .map_or(&[], |entry| entry.inputs.as_slice())
+    }
+
+    /// The action's inputs for showing to the player, e.g. `Delete/Pad East`.
+    pub fn describe(&self, action: HandActions) -> String {
+        let inputs: Vec<String> = self.get(action).iter().map(Binding::to_string).collect();
+
+        if inputs.is_empty() {
+            "unbound".to_string()
+        } else {
+            inputs.join("/")
+        }
+    }
+
+    /// Binds `input` to `action` in place of the action's inputs of the same kind, a new key
+    /// replaces the old key but keeps the mouse and gamepad buttons.
+    pub fn rebind(&mut self, action: HandActions, input: Binding) {
+        if let Some(entry) = self.actions.iter_mut().find(|entry| entry.action == action) {
+            entry.inputs.retain(|other| !other.same_kind(&input));
+            entry.inputs.push(input);
+        }
+    }
+
+    /// Inputs `action` shares with another action, or with the fixed movement keys.
+    pub fn conflicts(&self, action: HandActions) -> Vec<(Binding, HandActions)> {
+        let mut conflicts = Vec::new();
+
+        for input in self.get(action) {
+            if let Binding::Key(key) = input {
+                if MOVE_KEYS.contains(key) {
+                    conflicts.push((*input, HandActions::Move));
+                }
+            }
+
+            for other in self.actions.iter().filter(|other| other.action != action) {
+                if other.inputs.contains(input) {
+                    conflicts.push((*input, other.action));
+                }
+            }
+        }
+
+        conflicts
+    }
+
+    pub fn input_map(&self) -> InputMap<HandActions> {
+        let mut input_map = InputMap::default();
+
+        for entry in self.actions.iter() {
+            for input in entry.inputs.iter() {
+                match *input {
+                    Binding::Key(key) => input_map.insert(entry.action, key),
+                    Binding::Mouse(button) => input_map.insert(entry.action, button),
+                    Binding::Gamepad(button) => input_map.insert(entry.action, button),
+                };
+            }
+        }
+
+        input_map.insert(HandActions::RotateLeft, MouseScrollDirection::UP);
+        input_map.insert(HandActions::RotateRight, MouseScrollDirection::DOWN);
+        input_map.insert(HandActions::Move, GamepadStick::LEFT);
+        input_map.insert(HandActions::Move, KeyboardVirtualDPad::WASD);
+        input_map.insert(HandActions::Move, KeyboardVirtualDPad::ARROW_KEYS);
+
+        input_map
+    }
+}
+
+pub(super) fn apply_bindings(
+    mut commands: Commands,
+    hands: Query<Entity, With<CurrentHand>>,
+    bindings: Res<Bindings>,
+) {
+    for hand in hands.iter() {
+        commands.entity(hand).insert(bindings.input_map());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_have_no_conflicts() {
+        let bindings = Bindings::default();
+
+        for entry in bindings.actions.iter() {
+            assert!(bindings.conflicts(entry.action).is_empty(), "{:?} conflicts", entry.action);
+        }
+    }
+
+    #[test]
+    fn shared_input_conflicts_both_ways() {
+        let mut bindings = Bindings::default();
+        bindings.rebind(HandActions::MuteGhost, Binding::Key(KeyCode::KeyE));
+
+        assert_eq!(bindings.conflicts(HandActions::MuteGhost), [(Binding::Key(KeyCode::KeyE), HandActions::Grab)]);
+        assert_eq!(bindings.conflicts(HandActions::Grab), [(Binding::Key(KeyCode::KeyE), HandActions::MuteGhost)]);
+    }
+
+    #[test]
+    fn movement_keys_conflict() {
+        let mut bindings = Bindings::default();
+        bindings.rebind(HandActions::Reload, Binding::Key(KeyCode::KeyW));
+
+        assert_eq!(bindings.conflicts(HandActions::Reload), [(Binding::Key(KeyCode::KeyW), HandActions::Move)]);
+    }
+
+    #[test]
+    fn rebind_replaces_the_same_kind() {
+        let mut bindings = Bindings::default();
+        bindings.rebind(HandActions::Grab, Binding::Key(KeyCode::KeyG));
+
+        assert_eq!(bindings.get(HandActions::Grab), [
+            Binding::Mouse(MouseButton::Left),
+            Binding::Gamepad(GamepadButtonType::South),
+            Binding::Key(KeyCode::KeyG),
+        ]);
+
+        bindings.rebind(HandActions::Grab, Binding::Mouse(MouseButton::Right));
+
+        assert_eq!(bindings.get(HandActions::Grab), [
+            Binding::Gamepad(GamepadButtonType::South),
+            Binding::Key(KeyCode::KeyG),
+            Binding::Mouse(MouseButton::Right),
+        ]);
+    }
+
+    #[test]
+    fn merged_keeps_new_actions() {
+        let saved = Bindings {
+            actions: vec![ActionBindings { action: HandActions::Grab, inputs: vec![Binding::Key(KeyCode::KeyG)] }],
+        };
+
+        let bindings = Bindings::merged(saved);
+
+        assert_eq!(bindings.get(HandActions::Grab), [Binding::Key(KeyCode::KeyG)]);
+        assert_eq!(bindings.describe(HandActions::MuteGhost), "KeyM");
+    }
+}
@@ -0,0 +1,301 @@
+use std::fmt;
+
+use bevy::asset::{io::Reader, AssetLoadFailedEvent, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::ecs::system::EntityCommands;
+use bevy::reflect::TypePath;
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// The hand every hand is spawned from, live and ghost alike.
+const DEFAULT_HAND: &str = "hands/default.hand.toml";
+
+/// What a hand looks like and how it reaches, from a `.hand.toml` file. Sizes are in sprite
+/// pixels, so a new sprite only needs its own numbers. Also kept on each hand spawned from it.
+#[derive(Serialize, Deserialize, Asset, TypePath, Component, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HandDefinition {
+    pub sprite: String,
+    pub scale: f32,
+    /// [width, height] of the area objects are grabbed from, centered on the anchor
+    pub sensor: [f32; 2],
+    /// [x, y] of the grab point from the middle of the sprite, it follows the cursor and holds
+    /// objects
+    pub anchor: [f32; 2],
+    #[serde(default)]
+    pub movement: Movement,
+}
+
+/// Stiffest spring a hand can use, past about 50 per second it overshoots more every fixed tick
+/// until it flies off.
+const MAX_SPRING_GAIN: f32 = 40.0;
+
+/// How a hand chases its goal, in world pixels and seconds.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct Movement {
+    /// how strongly the hand pulls toward its goal, per second
+    pub gain: f32,
+    pub max_speed: f32,
+    /// how quickly it can speed up, slow down or turn
+    pub max_acceleration: f32,
+    /// a critically damped spring with `gain` as its frequency, it eases in without overshooting
+    /// where plain gain heads straight for the goal
+    pub spring: bool,
+}
+
+impl Default for Movement {
+    fn default() -> Self {
+        Self {
+            gain: 5.0,
+            max_speed: 2500.0,
+            max_acceleration: 20000.0,
+            spring: false,
+        }
+    }
+}
+
+impl Movement {
+    fn validate(&self) -> Result<(), HandError> {
+        let numbers = [
+            ("gain", self.gain),
+            ("max_speed", self.max_speed),
+            ("max_acceleration", self.max_acceleration),
+        ];
+
+        for (field, number) in numbers {
+            if !(number > 0.0 && number.is_finite()) {
+                return Err(HandError(format!("movement.{}: expected a positive number, found {}", field, number)));
+            }
+        }
+
+        if self.spring && self.gain > MAX_SPRING_GAIN {
+            return Err(HandError(format!(
+                "movement.gain: a spring is at most {}, found {}", MAX_SPRING_GAIN, self.gain
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// The velocity after `dt` of moving at `velocity` while `offset` away from the goal.
+    pub fn step(&self, offset: Vec2, velocity: Vec2, dt: f32) -> Vec2 {
+        let wanted = if self.spring {
+            let acceleration = self.gain * self.gain * offset - 2.0 * self.gain * velocity;
+            velocity + acceleration * dt
+        } else {
+            offset * self.gain
+        };
+
+        let wanted = wanted.clamp_length_max(self.max_speed);
+
+        velocity + (wanted - velocity).clamp_length_max(self.max_acceleration * dt)
+    }
+}
+
+/// Why a hand definition can't be used.
+#[derive(Debug, Clone)]
+pub struct HandError(pub String);
+
+impl fmt::Display for HandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for HandError {}
+
+impl HandDefinition {
+    pub fn parse(text: &str) -> Result<Self, HandError> {
+        let definition: Self = toml::from_str(text).map_err(|e| HandError(e.message().to_string()))?;
+        definition.validate()?;
+
+        Ok(definition)
+    }
+
+    /// Checks the numbers a hand-edited file could get wrong, a hand with no size can't grab
+    /// anything.
+    pub fn validate(&self) -> Result<(), HandError> {
+        if !(self.scale > 0.0 && self.scale.is_finite()) {
+            return Err(HandError(format!("scale: expected a positive number, found {}", self.scale)));
+        }
+
+        if !self.sensor.iter().all(|size| *size > 0.0 && size.is_finite()) {
+            return Err(HandError(format!(
+                "sensor: expected a positive width and height, found [{}, {}]", self.sensor[0], self.sensor[1]
+            )));
+        }
+
+        if !self.anchor.iter().all(|position| position.is_finite()) {
+            return Err(HandError(format!(
+                "anchor: expected numbers, found [{}, {}]", self.anchor[0], self.anchor[1]
+            )));
+        }
+
+        self.movement.validate()
+    }
+
+    /// The grab point from the hand's position in the world.
+    pub fn anchor(&self) -> Vec2 {
+        Vec2::from(self.anchor) * self.scale
+    }
+
+    /// Spawns a hand with a sensor at its grab point, for the caller to make live or ghost.
+    pub(super) fn spawn<'a>(&self, commands: &'a mut Commands, asset_server: &AssetServer) -> EntityCommands<'a> {
+        let mut hand = commands.spawn((
+            HandBundle::default(),
+            SpriteBundle {
+                texture: asset_server.load(self.sprite.clone()),
+                transform: Transform::from_scale(Vec3::splat(self.scale)),
+                ..default()
+            },
+            self.clone(),
+        ));
+
+        // a child of the scaled hand, so its numbers stay in sprite pixels
+        hand.with_children(|parent| {
+            parent.spawn((
+                TransformBundle::from_transform(Transform::from_xyz(self.anchor[0], self.anchor[1], 0.0)),
+                Collider::rectangle(self.sensor[0], self.sensor[1]),
+                Sensor,
+            ));
+        });
+
+        hand
+    }
+}
+
+/// The definition hands are spawned from.
+#[derive(Resource)]
+pub struct HandHandle(pub Handle<HandDefinition>);
+
+#[derive(Default)]
+struct HandLoader;
+
+impl AssetLoader for HandLoader {
+    type Asset = HandDefinition;
+    type Settings = ();
+    type Error = HandError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<HandDefinition, HandError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text).await
+            .map_err(|e| HandError(e.to_string()))?;
+
+        HandDefinition::parse(&text)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["hand.toml"]
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app
+        .init_asset::<HandDefinition>()
+        .init_asset_loader::<HandLoader>()
+        .add_systems(Startup, load_hand)
+        .add_systems(Update, report_hand_errors);
+}
+
+fn load_hand(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(HandHandle(asset_server.load(DEFAULT_HAND)));
+}
+
+// without a definition no hand is spawned, so say why
+fn report_hand_errors(mut ev_failed: EventReader<AssetLoadFailedEvent<HandDefinition>>) {
+    for ev in ev_failed.read() {
+        error!("no hand to play with, {}", ev.error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFAULT: &str = include_str!("../../assets/hands/default.hand.toml");
+
+    #[test]
+    fn default_hand_is_valid() {
+        HandDefinition::parse(DEFAULT).unwrap();
+    }
+
+    #[test]
+    fn rejects_sizes_that_are_not_positive() {
+        let definition = HandDefinition::parse(DEFAULT).unwrap();
+
+        let cases = [
+            ("scale", HandDefinition { scale: 0.0, ..definition.clone() }),
+            ("sensor", HandDefinition { sensor: [400.0, -1.0], ..definition.clone() }),
+            ("anchor", HandDefinition { anchor: [f32::NAN, 0.0], ..definition.clone() }),
+        ];
+
+        for (field, definition) in cases {
+            assert!(definition.validate().unwrap_err().0.starts_with(field), "accepted a bad {}", field);
+        }
+    }
+
+    #[test]
+    fn rejects_movement_that_is_not_positive_or_too_stiff() {
+        let definition = HandDefinition::parse(DEFAULT).unwrap();
+        let movement = |movement: Movement| HandDefinition { movement, ..definition.clone() }.validate();
+
+        assert!(movement(Movement { gain: 0.0, ..default() }).unwrap_err().0.starts_with("movement.gain"));
+        assert!(movement(Movement { max_speed: -1.0, ..default() }).unwrap_err().0.starts_with("movement.max_speed"));
+        assert!(movement(Movement { max_acceleration: f32::NAN, ..default() }).unwrap_err().0.starts_with("movement.max_acceleration"));
+
+        assert!(movement(Movement { gain: 100.0, ..default() }).is_ok());
+        assert!(movement(Movement { gain: 100.0, spring: true, ..default() }).is_err());
+    }
+
+    const DT: f32 = 1.0 / 64.0;
+
+    #[test]
+    fn gain_heads_for_the_goal() {
+        let movement = Movement { max_acceleration: f32::INFINITY, ..default() };
+
+        assert_eq!(movement.step(Vec2::new(100.0, 0.0), Vec2::ZERO, DT), Vec2::new(500.0, 0.0));
+        assert_eq!(movement.step(Vec2::ZERO, Vec2::new(300.0, 0.0), DT), Vec2::ZERO);
+    }
+
+    #[test]
+    fn speed_is_capped() {
+        let movement = Movement { max_acceleration: f32::INFINITY, ..default() };
+
+        let velocity = movement.step(Vec2::new(0.0, 10000.0), Vec2::ZERO, DT);
+
+        assert!((velocity.length() - movement.max_speed).abs() < 1e-2);
+    }
+
+    #[test]
+    fn acceleration_is_capped() {
+        let movement = Movement::default();
+
+        let velocity = movement.step(Vec2::new(100.0, 0.0), Vec2::ZERO, DT);
+
+        assert!((velocity.x - movement.max_acceleration * DT).abs() < 1e-3);
+    }
+
+    #[test]
+    fn spring_settles_without_overshooting() {
+        let movement = Movement { spring: true, ..default() };
+
+        let goal = 200.0;
+        let mut position = 0.0;
+        let mut velocity = Vec2::ZERO;
+
+        for _ in 0..640 {
+            velocity = movement.step(Vec2::new(goal - position, 0.0), velocity, DT);
+            position += velocity.x * DT;
+
+            assert!(position <= goal + 0.5);
+        }
+
+        assert!((goal - position).abs() < 1.0);
+    }
+}
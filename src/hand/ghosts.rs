@@ -0,0 +1,167 @@
+use camera::cursor_world_position;
+
+use super::*;
+
+// how close to a ghost hand's centre a click has to be to select it
+const SELECT_RADIUS: f32 = 80.0;
+
+const SELECTED_COLOR: Color = Color::srgb(1.0, 0.85, 0.3);
+const MUTED_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.35);
+
+/// The ghost hand picked with the middle mouse button, recording replaces it.
+#[derive(Resource, Default)]
+pub struct SelectedGhost(pub Option<Entity>);
+
+/// Most ghost hands the current level allows, `None` for no limit.
+#[derive(Resource, Default)]
+pub struct GhostLimit(pub Option<u32>);
+
+impl GhostLimit {
+    pub fn allows(&self, ghosts: usize) -> bool {
+        self.0.map_or(true, |limit| ghosts < limit as usize)
+    }
+}
+
+/// A ghost hand that is paused, it holds still and doesn't grab until unmuted.
+#[derive(Component)]
+pub struct Muted;
+
+pub(super) fn register(app: &mut App) {
+    app
+        .init_resource::<SelectedGhost>()
+        .init_resource::<GhostLimit>()
+        .add_systems(Update, (
+            (select_ghost, delete_ghost, mute_ghost).chain().run_if(in_play),
+            forget_missing,
+            ghost_colors,
+        ).chain());
+}
+
+/// Despawns a ghost hand, dropping whatever it holds.
+pub(super) fn despawn_ghost(
+    commands: &mut Commands,
+    ghost: Entity,
+    grabbing: Option<&Grabbing>,
+    joints: &Query<&RevoluteJoint>,
+) {
+    if let Some(grabbing) = grabbing {
+        if let Ok(joint) = joints.get(grabbing.0) {
+            commands.entity(joint.entity2)
+                .insert(Grabbable)
+                .remove::<(Grabbed, SweptCcd)>();
+        }
+
+        commands.entity(grabbing.0).despawn();
+    }
+
+    commands.entity(ghost).despawn_recursive();
+}
+
+fn select_ghost(
+    hands: Query<&ActionState<HandActions>, With<CurrentHand>>,
+    ghosts: Query<(Entity, &Transform), With<Playback>>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut selected: ResMut<SelectedGhost>,
+) {
+    let Ok(action) = hands.get_single() else {
+        return;
+    };
+
+    if !action.just_pressed(&HandActions::SelectGhost) {
+        return;
+    }
+
+    let Some(cursor) = cursor_world_position(&windows, &cameras) else {
+        return;
+    };
+
+    // clicking away from every ghost clears the selection
+    selected.0 = ghosts.iter()
+        .map(|(ghost, transform)| (ghost, transform.translation.truncate().distance(cursor)))
+        .filter(|(_, distance)| *distance <= SELECT_RADIUS)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(ghost, _)| ghost);
+}
+
+fn delete_ghost(
+    mut commands: Commands,
+    hands: Query<&ActionState<HandActions>, With<CurrentHand>>,
+    ghosts: Query<Option<&Grabbing>, With<Playback>>,
+    joints: Query<&RevoluteJoint>,
+    mut selected: ResMut<SelectedGhost>,
+) {
+    let Ok(action) = hands.get_single() else {
+        return;
+    };
+
+    if !action.just_pressed(&HandActions::DeleteGhost) {
+        return;
+    }
+
+    let Some(ghost) = selected.0.take() else {
+        return;
+    };
+
+    if let Ok(grabbing) = ghosts.get(ghost) {
+        despawn_ghost(&mut commands, ghost, grabbing, &joints);
+    }
+}
+
+fn mute_ghost(
+    mut commands: Commands,
+    hands: Query<&ActionState<HandActions>, With<CurrentHand>>,
+    ghosts: Query<Has<Muted>, With<Playback>>,
+    selected: Res<SelectedGhost>,
+) {
+    let Ok(action) = hands.get_single() else {
+        return;
+    };
+
+    if !action.just_pressed(&HandActions::MuteGhost) {
+        return;
+    }
+
+    let Some(ghost) = selected.0 else {
+        return;
+    };
+
+    match ghosts.get(ghost) {
+        Ok(true) => {
+            commands.entity(ghost).remove::<Muted>();
+        }
+        Ok(false) => {
+            commands.entity(ghost).insert(Muted);
+        }
+        Err(_) => {}
+    }
+}
+
+// the selected ghost can be despawned by loading a level or replacing it
+fn forget_missing(
+    ghosts: Query<(), With<Playback>>,
+    mut selected: ResMut<SelectedGhost>,
+) {
+    if selected.0.is_some_and(|ghost| ghosts.get(ghost).is_err()) {
+        selected.0 = None;
+    }
+}
+
+fn ghost_colors(
+    mut ghosts: Query<(Entity, &mut Sprite, Has<Muted>), With<Playback>>,
+    selected: Res<SelectedGhost>,
+) {
+    for (ghost, mut sprite, muted) in ghosts.iter_mut() {
+        let color = if selected.0 == Some(ghost) {
+            SELECTED_COLOR
+        } else if muted {
+            MUTED_COLOR
+        } else {
+            Color::WHITE
+        };
+
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::VecDeque;
+
+use super::*;
+
+/// Presses of the hand's actions, queued until a fixed tick handles them so none are lost or
+/// doubled however many ticks run in a frame. The live hand fills this from its input, ghost
+/// hands from their recording.
+#[derive(Component, Default)]
+pub struct HandInput {
+    // a grab press replaying a throw carries the velocity it throws with
+    queued: VecDeque<(HandActions, Option<Vec2>)>,
+    // handled during the current tick
+    tick: Vec<HandActions>,
+    // recorded velocity the grab press handled this tick throws with
+    replayed_throw: Option<Vec2>,
+    // velocity an object was let go of with during the current tick
+    throw: Option<Vec2>,
+}
+
+impl HandInput {
+    pub fn queue(&mut self, action: HandActions) {
+        self.queued.push_back((action, None));
+    }
+
+    /// Queues a grab press that throws whatever it lets go of at `velocity`, for replaying a
+    /// recorded throw.
+    pub fn queue_throw(&mut self, velocity: Vec2) {
+        self.queued.push_back((HandActions::Grab, Some(velocity)));
+    }
+
+    /// Velocity the object let go of this tick was thrown with, `None` if it just dropped.
+    pub fn throw(&self) -> Option<Vec2> {
+        self.throw
+    }
+
+    pub(super) fn set_throw(&mut self, velocity: Option<Vec2>) {
+        self.throw = velocity;
+    }
+
+    /// Recorded velocity this tick's grab press throws with, if it's replaying a throw.
+    pub(super) fn replayed_throw(&self) -> Option<Vec2> {
+        self.replayed_throw
+    }
+
+    /// The presses handled this tick, in order.
+    pub fn tick(&self) -> &[HandActions] {
+        &self.tick
+    }
+
+    pub fn just_pressed(&self, action: HandActions) -> bool {
+        self.tick.contains(&action)
+    }
+
+    // takes the queued presses for this tick, at most one per action so a quick double press
+    // grabs and drops on separate ticks, each keeping its own throw
+    fn step(&mut self) {
+        self.tick.clear();
+        self.replayed_throw = None;
+        self.throw = None;
+
+        let mut remaining = VecDeque::new();
+
+        for (action, velocity) in self.queued.drain(..) {
+            if self.tick.contains(&action) || remaining.iter().any(|(other, _)| *other == action) {
+                remaining.push_back((action, velocity));
+            } else {
+                self.tick.push(action);
+
+                if velocity.is_some() {
+                    self.replayed_throw = velocity;
+                }
+            }
+        }
+
+        self.queued = remaining;
+    }
+}
+
+impl HandActions {
+    /// Actions that act on the world, these are recorded and replayed by ghost hands.
+    pub const RECORDED: [HandActions; 3] = [HandActions::Grab, HandActions::RotateLeft, HandActions::RotateRight];
+}
+
+pub(super) fn buffer_actions(
+    mut hands: Query<(&ActionState<HandActions>, &mut HandInput), With<CurrentHand>>,
+) {
+    for (action, mut input) in hands.iter_mut() {
+        for recorded in HandActions::RECORDED {
+            if action.just_pressed(&recorded) {
+                input.queue(recorded);
+            }
+        }
+    }
+}
+
+pub(super) fn step_input(
+    mut hands: Query<&mut HandInput>,
+) {
+    for mut input in hands.iter_mut() {
+        input.step();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_double_press_takes_two_ticks() {
+        let mut input = HandInput::default();
+        input.queue(HandActions::Grab);
+        input.queue(HandActions::Grab);
+
+        input.step();
+        assert!(input.just_pressed(HandActions::Grab));
+
+        input.step();
+        assert!(input.just_pressed(HandActions::Grab));
+
+        input.step();
+        assert!(input.tick().is_empty());
+    }
+
+    #[test]
+    fn a_throw_stays_with_its_press() {
+        let mut input = HandInput::default();
+        input.queue(HandActions::Grab);
+        input.queue_throw(Vec2::new(300.0, 0.0));
+
+        input.step();
+        assert_eq!(input.replayed_throw(), None);
+
+        input.step();
+        assert!(input.just_pressed(HandActions::Grab));
+        assert_eq!(input.replayed_throw(), Some(Vec2::new(300.0, 0.0)));
+
+        input.step();
+        assert_eq!(input.replayed_throw(), None);
+    }
+}
@@ -1,14 +1,28 @@
 use super::*;
 
 use avian2d::prelude::*;
+use camera::cursor_world_position;
 use leafwing_input_manager::prelude::*;
 use level::ColliderInfo;
-use object::{GrabInteractions, Grabbable, Grabbed, Object, ObjectInfo};
-use recording::Recording;
+use object::{GrabInteractions, Grabbable, Grabbed, Item, Object, ObjectInfo, Throwable};
+use serde::{Deserialize, Serialize};
+use state::in_play;
 
+mod bindings;
+mod definition;
+mod ghosts;
+mod input;
 mod recording;
+mod saved;
+mod throw;
 
-pub use recording::Playback;
+pub use bindings::{ActionBindings, Binding, Bindings};
+pub use definition::{HandDefinition, HandHandle, Movement};
+pub use ghosts::{GhostLimit, Muted, SelectedGhost};
+pub use input::HandInput;
+pub use recording::{GhostMotion, Interpolation, LoopChoice, LoopClock, LoopLength, Playback, Recording, RecordingState, TimedAction, TimedThrow, WaitForLoop, DEFAULT_LOOP_LENGTH};
+pub use saved::{ImportRecording, RecordingError, SavedRecording};
+pub use throw::VelocityHistory;
 
 #[derive(Component)]
 pub struct Hand;
@@ -17,16 +31,35 @@ pub struct Hand;
 pub struct CurrentHand;
 
 #[derive(Component)]
-pub struct Grabbing(Entity);
+pub struct Grabbing(pub Entity);
 
 #[derive(Component)]
 pub struct Goal(Vec2);
 
-#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect)]
+/// On the object the live hand would pick up if grab were pressed now.
+#[derive(Component)]
+pub struct Hovered;
+
+#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect, Serialize, Deserialize)]
 pub enum HandActions {
+    /// steers the hand without a mouse, a stick or WASD/arrow keys
+    Move,
     Grab,
+    /// turns the held object a step counterclockwise, also the scroll wheel
+    RotateLeft,
+    /// turns the held object a step clockwise, also the scroll wheel
+    RotateRight,
     Record,
     Reload,
+    Export,
+    Import,
+    /// picks a short, medium or long loop for the next recording
+    CycleLoop,
+    /// switches between recording right away and at the start of the next loop
+    WaitForLoop,
+    SelectGhost,
+    DeleteGhost,
+    MuteGhost,
 }
 
 #[derive(Bundle)]
@@ -36,6 +69,8 @@ struct HandBundle {
     rigid_body: RigidBody,
     velocity: LinearVelocity,
     locked: LockedAxes,
+    input: HandInput,
+    history: VelocityHistory,
     hand: Hand,
 }
 
@@ -47,211 +82,367 @@ impl Default for HandBundle {
             rigid_body: RigidBody::Dynamic,
             velocity: LinearVelocity::ZERO,
             locked: LockedAxes::ROTATION_LOCKED,
+            input: HandInput::default(),
+            history: VelocityHistory::default(),
             hand: Hand,
         }
     }
 }
 
-const HAND_OFFSET: f32 = -200.0;
+// pixels per second the hand moves with the stick all the way over
+const MOVE_SPEED: f32 = 900.0;
+const HOVER_TINT: Color = Color::srgb(1.0, 1.0, 0.55);
+// radians a held object turns per rotate press
+const ROTATE_STEP: f32 = std::f32::consts::PI / 12.0;
+// objects thrown slower than this move under 10 pixels a tick and can't skip past a wall
+const SWEEP_SPEED: f32 = 640.0;
 
 pub(super) fn register(app: &mut App) {
     app.add_plugins(InputManagerPlugin::<HandActions>::default())
-        .add_systems(Startup, spawn_hand)
-        .add_systems(Update, (move_hand, grab, drop))
-        .add_systems(FixedUpdate, update_goal);
+        .init_resource::<Bindings>()
+        .add_systems(Update, (
+            spawn_hand.run_if(not(any_with_component::<CurrentHand>)),
+            input::buffer_actions.run_if(in_play),
+            highlight_candidate.run_if(in_play),
+            bindings::apply_bindings.run_if(resource_changed::<Bindings>),
+        ))
+        // everything that moves a hand runs on fixed ticks so a loop plays back the same every time
+        .add_systems(FixedUpdate, (
+            recording::tick_clock,
+            recording::playback,
+            input::step_input,
+            update_goal,
+            move_hand,
+            throw::sample_velocity,
+            (grab, drop),
+            rotate_held,
+            recording::recording,
+        ).chain().run_if(in_play));
 
+    definition::register(app);
+    ghosts::register(app);
     recording::register(app);
+    saved::register(app);
 }
 
+// once the hand's definition has loaded
 fn spawn_hand(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    bindings: Res<Bindings>,
+    handle: Res<HandHandle>,
+    definitions: Res<Assets<HandDefinition>>,
 ) {
-    //Input map
-    let mut input_map = InputMap::new([
-        (HandActions::Grab, MouseButton::Left),
-        (HandActions::Record, MouseButton::Right),
-    ]);
-
-    input_map.insert(HandActions::Record, KeyCode::Space);
-    input_map.insert(HandActions::Reload, KeyCode::KeyR);
-
-    let texture = asset_server.load("hand.png");
+    let Some(definition) = definitions.get(&handle.0) else {
+        return;
+    };
 
     // Spawns the hand!
-    commands.spawn((
-        HandBundle::default(),
-        SpriteBundle {
-            texture,
-            transform: Transform::from_scale(Vec3::new(0.5, 0.5, 0.5)),
-            ..default()
-        },
-        InputManagerBundle::with_map(input_map),
+    definition.spawn(&mut commands, &asset_server).insert((
+        InputManagerBundle::with_map(bindings.input_map()),
         CurrentHand,
-    )).with_children(|parent| {
-        parent.spawn((
-            TransformBundle::from_transform(Transform::from_xyz(0.0, -HAND_OFFSET, 0.0)),
-            Collider::rectangle(400.0, 600.0),
-            Sensor,
-        ));
-    });
+    ));
 }
 
+// The live hand throws with its own recent velocity, ghost hands with the one recorded for the drop.
 fn drop(
     mut commands: Commands,
-    hands: Query<(&ActionState<HandActions>, Entity, &Grabbing), With<Hand>>,
-    joints: Query<&FixedJoint>,
-    mut is_recording: Query<&mut Recording, With<CurrentHand>>,
+    mut hands: Query<(&mut HandInput, &VelocityHistory, Has<Playback>, Entity, &Grabbing), With<Hand>>,
+    joints: Query<&RevoluteJoint>,
+    mut throwables: Query<(&Throwable, &mut LinearVelocity), Without<Hand>>,
 ) {
-    for (action, hand, grabbing) in hands.iter() {
-        if !action.just_pressed(&HandActions::Grab) {
+    for (mut input, history, ghost, hand, grabbing) in hands.iter_mut() {
+        if !input.just_pressed(HandActions::Grab) {
             continue;
         }
-
-        if let Ok(mut recording) = is_recording.get_mut(hand) {
-            let elapsed = recording.timer.elapsed();
-    
-            recording.grabs.push(elapsed);
-        }
     
         let mut hand_commands = commands.entity(hand);
     
         hand_commands.remove::<Grabbing>();
         let joint = grabbing.0;
-    
-        let object = joints.get(joint).unwrap().entity1;
-    
-        let mut object_commands = commands.entity(object);
-        object_commands.insert(Grabbable);
-        object_commands.remove::<Grabbed>();
+
+        // already gone if a collector took what it held
+        let Ok(joint_info) = joints.get(joint) else {
+            continue;
+        };
+
+        let object = joint_info.entity2;
+
+        // gone if the editor deleted it while it was held
+        if let Some(mut object_commands) = commands.get_entity(object) {
+            object_commands.insert(Grabbable);
+            object_commands.remove::<Grabbed>();
+        }
 
         commands.entity(joint).despawn();
+
+        let velocity = if ghost { input.replayed_throw() } else { Some(history.average()) };
+
+        let thrown = match (velocity, throwables.get_mut(object)) {
+            (Some(velocity), Ok((throwable, mut object_velocity))) => {
+                object_velocity.0 = velocity.clamp_length_max(throwable.max_speed);
+                Some(object_velocity.0)
+            }
+            _ => None,
+        };
+
+        // fast throws keep sweeping until they're picked up again, slower ones can't tunnel
+        if thrown.map_or(true, |velocity| velocity.length() < SWEEP_SPEED) {
+            commands.entity(object).remove::<SweptCcd>();
+        }
+
+        input.set_throw(thrown);
+    }
+}
+
+// Grabbable objects `grab` can pick up, it copies their object info and collider.
+type GrabCandidates<'w, 's> = Query<'w, 's, &'static GlobalTransform, (With<Grabbable>, With<ObjectInfo>, With<ColliderInfo>)>;
+
+// The grabbable object nearest the grab point of a hand with these children, the one on top if
+// several are as near to the pixel.
+fn grab_candidate(
+    children: &Children,
+    sensors: &Query<(&CollidingEntities, &GlobalTransform)>,
+    grabbable: &GrabCandidates,
+) -> Option<Entity> {
+    children.iter()
+        .filter_map(|child| sensors.get(*child).ok())
+        .flat_map(|(colliding_entities, sensor)| {
+            let grab_point = sensor.translation().truncate();
+
+            colliding_entities.0.iter().filter_map(move |object| {
+                let position = grabbable.get(*object).ok()?.translation();
+
+                Some((*object, position.truncate().distance(grab_point).round(), position.z))
+            })
+        })
+        .min_by(|(_, a, a_z), (_, b, b_z)| a.total_cmp(b).then(b_z.total_cmp(a_z)))
+        .map(|(object, ..)| object)
+}
+
+fn highlight_candidate(
+    mut commands: Commands,
+    hands: Query<(&Children, Has<Grabbing>), With<CurrentHand>>,
+    sensors: Query<(&CollidingEntities, &GlobalTransform)>,
+    grabbable: GrabCandidates,
+    hovered: Query<Entity, With<Hovered>>,
+    mut sprites: Query<&mut Sprite>,
+) {
+    let candidate = hands.get_single().ok()
+        .filter(|(_, grabbing)| !grabbing)
+        .and_then(|(children, _)| grab_candidate(children, &sensors, &grabbable));
+
+    for object in hovered.iter().filter(|object| Some(*object) != candidate) {
+        commands.entity(object).remove::<Hovered>();
+
+        if let Ok(mut sprite) = sprites.get_mut(object) {
+            sprite.color = Color::WHITE;
+        }
+    }
+
+    let Some(candidate) = candidate.filter(|candidate| !hovered.contains(*candidate)) else {
+        return;
+    };
+
+    commands.entity(candidate).insert(Hovered);
+
+    if let Ok(mut sprite) = sprites.get_mut(candidate) {
+        sprite.color = HOVER_TINT;
     }
 }
 
 fn grab(
     mut commands: Commands,
     hands: Query<
-        (&ActionState<HandActions>, Entity, &Children),
+        (&HandInput, Entity, &Children, &HandDefinition),
         (With<Hand>, Without<Grabbing>),
     >,
-    collisions: Query<&CollidingEntities>,
-    objects: Query<(&Transform, &ObjectInfo, &ColliderInfo), With<Grabbable>>,
-    //TODO! only one hand can record current hand isn't required
-    mut is_recording: Query<&mut Recording, With<CurrentHand>>,
+    sensors: Query<(&CollidingEntities, &GlobalTransform)>,
+    grabbable: GrabCandidates,
+    objects: Query<(&Transform, &ObjectInfo, &ColliderInfo, Option<&Item>, Option<&Throwable>), With<Grabbable>>,
     asset_server: Res<AssetServer>,
 ) {
-    for (action, hand, children) in hands.iter() {
-        if !action.just_pressed(&HandActions::Grab) {
+    for (input, hand, children, definition) in hands.iter() {
+        if !input.just_pressed(HandActions::Grab) {
             continue;
         }
-    
-        if let Ok(mut recording) = is_recording.get_mut(hand) {
-            let elapsed = recording.timer.elapsed();
-    
-            recording.grabs.push(elapsed);
-        }
-    
-        for child in children.iter() {
-            let colliding_entities = collisions.get(*child).unwrap();
-    
-            if colliding_entities.is_empty() {
-                continue;
-            }
-    
-            let mut object = None;
-            //TODO! stupidest thing I've ever seen HOW DO YOU CODE IN RUST
-            for x in colliding_entities.0.iter() {
-                if objects.get(*x).is_ok() {
-                    object = Some(x);
-                    break;
-                }
-            }
-    
-            if object.is_none() {
-                continue;
-            }
 
-            let mut object = *object.unwrap();
+        let Some(mut object) = grab_candidate(children, &sensors, &grabbable) else {
+            continue;
+        };
 
-            let (transform, interaction, collider_info) = objects.get(object).unwrap();
+        let Ok((transform, interaction, collider_info, item, throwable)) = objects.get(object) else {
+            continue;
+        };
 
-            match interaction.grab {
-                GrabInteractions::Grab => {
-                    let mut object_commands = commands.entity(object);
-                    object_commands.remove::<Grabbable>();
-                    object_commands.insert(Grabbed(hand));
-    
-                    let mut joint = FixedJoint::new(object, hand);
-                    joint.local_anchor2 = Vec2::new(0.0, -HAND_OFFSET);
-            
-                    let joint_commands = commands.spawn(joint);
-                    let joint_entity = joint_commands.id();
-            
-                    let mut hand_commands = commands.entity(hand);
-                    hand_commands.insert(Grabbing(joint_entity));
-                },
-                GrabInteractions::Spawn => {
-                    let texture = asset_server.load(interaction.texture_name.clone());
-                    object = commands.spawn((
-                        SpriteBundle {
-                            texture,
-                            transform: *transform,
-                            ..default()
-                        },
-                        RigidBody::Dynamic,
-                        LinearDamping(1.0),
-                        Object,
-                        collider_info.clone(),
-                        ObjectInfo {
-                            grab: GrabInteractions::Grab,
-                            texture_name: interaction.texture_name.clone(),
-                        }
-                    )).id();
-
-                    let mut object_commands = commands.entity(object);
-                    object_commands.insert(Grabbed(hand));
-    
-                    let mut joint = FixedJoint::new(object, hand);
-                    joint.local_anchor2 = Vec2::new(0.0, -HAND_OFFSET);
-            
-                    let joint_commands = commands.spawn(joint);
-                    let joint_entity = joint_commands.id();
-            
-                    let mut hand_commands = commands.entity(hand);
-                    hand_commands.insert(Grabbing(joint_entity));
-                },
-            }
+        match interaction.grab {
+            GrabInteractions::Grab => {
+                let mut object_commands = commands.entity(object);
+                object_commands.remove::<Grabbable>();
+                // held objects can move fast enough to pass through walls in a tick
+                object_commands.insert((Grabbed(hand), SweptCcd::default()));
+
+                let joint_commands = commands.spawn(hold_joint(hand, object, transform, definition));
+                let joint_entity = joint_commands.id();
+        
+                let mut hand_commands = commands.entity(hand);
+                hand_commands.insert(Grabbing(joint_entity));
+            },
+            GrabInteractions::Spawn => {
+                let texture = asset_server.load(interaction.texture_name.clone());
+                object = commands.spawn((
+                    SpriteBundle {
+                        texture,
+                        transform: *transform,
+                        ..default()
+                    },
+                    RigidBody::Dynamic,
+                    LinearDamping(1.0),
+                    Object,
+                    collider_info.clone(),
+                    ObjectInfo {
+                        grab: GrabInteractions::Grab,
+                        texture_name: interaction.texture_name.clone(),
+                    }
+                )).id();
+
+                let mut object_commands = commands.entity(object);
+                object_commands.insert((Grabbed(hand), SweptCcd::default()));
+
+                // copies are the same item as the object they came from
+                if let Some(item) = item {
+                    object_commands.insert(item.clone());
+                }
+
+                if let Some(throwable) = throwable {
+                    object_commands.insert(throwable.clone());
+                }
+
+                let joint_commands = commands.spawn(hold_joint(hand, object, transform, definition));
+                let joint_entity = joint_commands.id();
+        
+                let mut hand_commands = commands.entity(hand);
+                hand_commands.insert(Grabbing(joint_entity));
+            },
         }
     }
 }
 
+// Pins `object` to the hand's grab point, the angle limits keep it turned the way it was grabbed
+// until it's rotated.
+fn hold_joint(hand: Entity, object: Entity, transform: &Transform, definition: &HandDefinition) -> RevoluteJoint {
+    let (angle, _, _) = transform.rotation.to_euler(EulerRot::ZYX);
+
+    RevoluteJoint::new(hand, object)
+        .with_local_anchor_1(definition.anchor())
+        .with_angle_limits(angle, angle)
+}
+
+fn rotate_held(
+    hands: Query<(&HandInput, &Grabbing), With<Hand>>,
+    mut joints: Query<&mut RevoluteJoint>,
+) {
+    use std::f32::consts::{PI, TAU};
+
+    for (input, grabbing) in hands.iter() {
+        let turn = match (input.just_pressed(HandActions::RotateLeft), input.just_pressed(HandActions::RotateRight)) {
+            (true, false) => ROTATE_STEP,
+            (false, true) => -ROTATE_STEP,
+            _ => continue,
+        };
+
+        let Ok(mut joint) = joints.get_mut(grabbing.0) else {
+            continue;
+        };
+
+        // kept between -PI and PI, the range the joint measures its angle in
+        let angle = joint.angle_limit.map_or(0.0, |limit| limit.min);
+        let angle = (angle + turn + PI).rem_euclid(TAU) - PI;
+
+        joint.angle_limit = Some(AngleLimit::new(angle, angle));
+    }
+}
+
 fn move_hand(
-    mut hands: Query<(&Transform, &mut LinearVelocity, &Goal), With<Hand>>,
+    mut hands: Query<(&Transform, &mut LinearVelocity, &Goal, &HandDefinition), With<Hand>>,
+    time: Res<Time>,
 ) {
-    for (transform, mut velocity, goal) in hands.iter_mut() {
+    for (transform, mut velocity, goal, definition) in hands.iter_mut() {
         let hand_position = transform.translation.truncate();
         let cursor_dir = goal.0 - hand_position;
 
-        velocity.x = cursor_dir.x * 5.0;
-        velocity.y = cursor_dir.y * 5.0;
+        velocity.0 = definition.movement.step(cursor_dir, velocity.0, time.delta_seconds());
     }
 }
 
+// The mouse puts the goal under the cursor whenever it moves, otherwise the move action pushes it
+// around at up to `MOVE_SPEED`.
 fn update_goal(
-    mut hands: Query<&mut Goal, With<CurrentHand>>,
-    windows: Query<&mut Window>,
+    mut hands: Query<(&mut Goal, &ActionState<HandActions>, &HandDefinition), With<CurrentHand>>,
+    windows: Query<&Window>,
     cameras: Query<(&Camera, &GlobalTransform)>,
+    mut last_cursor: Local<Option<Vec2>>,
+    time: Res<Time>,
 ) {
-    let window = windows.single();
-    let (camera, camera_transform) = cameras.single();
-    let mut goal = hands.single_mut();
-
-    if let Some(cursor_position) = window
-        .cursor_position()
-        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
-    {
-        let goal_position = Vec2::new(cursor_position.x, cursor_position.y + HAND_OFFSET);
-        *goal = Goal(goal_position);
+    let Ok((mut goal, action, definition)) = hands.get_single_mut() else {
+        return;
+    };
+
+    // from the grab point to the hand
+    let offset = -definition.anchor();
+
+    if let Some(cursor) = cursor_world_position(&windows, &cameras) {
+        if *last_cursor != Some(cursor) {
+            *last_cursor = Some(cursor);
+            *goal = Goal(cursor + offset);
+            return;
+        }
+    }
+
+    let movement = action.clamped_axis_pair(&HandActions::Move).map_or(Vec2::ZERO, |axis| axis.xy());
+
+    if movement == Vec2::ZERO {
+        return;
+    }
+
+    // keep the point the hand grabs with on screen
+    let half_screen = Vec2::new(SCREEN_W, SCREEN_H) / 2.0;
+    let grab_point = (goal.0 - offset + movement * MOVE_SPEED * time.delta_seconds())
+        .clamp(-half_screen, half_screen);
+
+    *goal = Goal(grab_point + offset);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::utils::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn untextured_objects_are_not_grab_candidates() {
+        let mut world = World::new();
+
+        // right at the grab point, but without a texture there's no object info to grab it by
+        let untextured = world.spawn((Grabbable, GlobalTransform::default())).id();
+        let card = world.spawn((
+            Grabbable,
+            GlobalTransform::from_xyz(50.0, 0.0, 0.0),
+            ObjectInfo { grab: GrabInteractions::Grab, texture_name: "card.png".to_string() },
+            ColliderInfo { name: "circle".to_string(), size: None },
+        )).id();
+
+        let sensor = world.spawn((
+            CollidingEntities(HashSet::from_iter([untextured, card])),
+            GlobalTransform::default(),
+        )).id();
+        let hand = world.spawn_empty().push_children(&[sensor]).id();
+
+        let candidate = world.run_system_once(move |hands: Query<&Children>, sensors: Query<(&CollidingEntities, &GlobalTransform)>, grabbable: GrabCandidates| {
+            grab_candidate(hands.get(hand).unwrap(), &sensors, &grabbable)
+        });
+
+        assert_eq!(candidate, Some(card));
     }
 }
@@ -1,141 +1,683 @@
 use std::time::Duration;
+use serde::{Deserialize, Serialize};
 use super::*;
+use ghosts::despawn_ghost;
 
+/// A press of an action at a loop phase.
+pub type TimedAction = (Duration, HandActions);
+
+/// The velocity the grab press at a loop phase threw what it let go of with.
+pub type TimedThrow = (Duration, Vec2);
+
+/// Samples closer than this many pixels to the path between their neighbours are dropped.
+const COMPRESS_TOLERANCE: f32 = 0.5;
+
+/// How a ghost hand's goal moves between recorded samples.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    /// straight lines between samples
+    #[default]
+    Linear,
+    /// a curve through the samples, smoother but can overshoot sharp turns
+    CatmullRom,
+}
+
+/// Interpolation used for new recordings.
+#[derive(Resource, Default)]
+pub struct GhostMotion(pub Interpolation);
+
+/// Where a [`Recording`] is in its life, a hand without one is idle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingState {
+    /// waiting for the start, e.g. the next loop
+    Countdown,
+    Recording,
+    /// done, the ghost hand spawns on the next fixed tick
+    Committing,
+}
+
+/// Present on the hand while it is recording. Pressing record again cancels it and no ghost
+/// hand is spawned.
 #[derive(Component)]
 pub struct Recording{
-    pub timer: Timer,
+    /// clock time the recording starts at, it may still be waiting for it
+    start: Duration,
+    length: Duration,
+    // [loop phase, goal]
     record: Vec<(Duration, Vec2)>,
-    pub grabs: Vec<Duration>
+    actions: Vec<TimedAction>,
+    throws: Vec<TimedThrow>,
+    /// ghost hand swapped out for this recording once it's done
+    replaces: Option<Entity>,
+}
+
+impl Recording {
+    pub fn state(&self, clock: &LoopClock) -> RecordingState {
+        let now = clock.elapsed();
+
+        if now < self.start {
+            RecordingState::Countdown
+        } else if now < self.start + self.length {
+            RecordingState::Recording
+        } else {
+            RecordingState::Committing
+        }
+    }
+
+    pub fn time_to_start(&self, clock: &LoopClock) -> Duration {
+        self.start.saturating_sub(clock.elapsed())
+    }
+
+    pub fn time_left(&self, clock: &LoopClock) -> Duration {
+        (self.start + self.length).saturating_sub(clock.elapsed())
+    }
+
+    /// How much of the recording is done, from 0 to 1.
+    pub fn progress(&self, clock: &LoopClock) -> f32 {
+        let done = clock.elapsed().saturating_sub(self.start).as_secs_f32();
+
+        (done / self.length.as_secs_f32().max(f32::EPSILON)).min(1.0)
+    }
 }
 
+/// Repeats a recording, times are phases of the [`LoopClock`] so every ghost hand with the
+/// same loop length stays in step with the others no matter when it was recorded.
 #[derive(Component)]
 pub struct Playback{
-    timer: Timer,
+    loop_length: Duration,
+    // sorted by phase
     record: Vec<(Duration, Vec2)>,
-    stored_record: Vec<(Duration, Vec2)>,
-    grabs: Vec<Duration>,
-    stored_grabs: Vec<Duration>,
+    // sorted by phase, actions at the same phase keep the order they happened in
+    actions: Vec<TimedAction>,
+    // sorted by phase, at the phase of the grab press that threw
+    throws: Vec<TimedThrow>,
+    interpolation: Interpolation,
+    // phase at the last update, actions between it and the current phase are due
+    last_phase: Option<Duration>,
+}
+
+impl Playback {
+    /// `record`, `actions` and `throws` are loop phases, anything past `loop_length` wraps
+    /// around. Samples that add nothing to the path are dropped.
+    pub fn new(
+        loop_length: Duration,
+        mut record: Vec<(Duration, Vec2)>,
+        mut actions: Vec<TimedAction>,
+        mut throws: Vec<TimedThrow>,
+        interpolation: Interpolation,
+    ) -> Self {
+        let wrap = |time: Duration| LoopClock { elapsed: time }.phase(loop_length);
+
+        for (time, _) in record.iter_mut() {
+            *time = wrap(*time);
+        }
+        for (time, ..) in actions.iter_mut() {
+            *time = wrap(*time);
+        }
+        for (time, _) in throws.iter_mut() {
+            *time = wrap(*time);
+        }
+
+        record.sort_by_key(|(time, _)| *time);
+        // stable, so presses on the same tick stay in order
+        actions.sort_by_key(|(time, ..)| *time);
+        throws.sort_by_key(|(time, _)| *time);
+
+        Self {
+            loop_length,
+            record: compress(&record, loop_length, interpolation),
+            actions,
+            throws,
+            interpolation,
+            last_phase: None,
+        }
+    }
+
+    pub fn loop_length(&self) -> Duration {
+        self.loop_length
+    }
+
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    /// The goal positions in loop order.
+    pub fn record(&self) -> impl Iterator<Item = &(Duration, Vec2)> {
+        self.record.iter()
+    }
+
+    /// The action presses in loop order.
+    pub fn actions(&self) -> impl Iterator<Item = &TimedAction> {
+        self.actions.iter()
+    }
+
+    /// The throws in loop order.
+    pub fn throws(&self) -> impl Iterator<Item = &TimedThrow> {
+        self.throws.iter()
+    }
+
+    fn throw_at(&self, phase: Duration) -> Option<Vec2> {
+        self.throws.iter().find(|(time, _)| *time == phase).map(|(_, velocity)| *velocity)
+    }
+
+    // the goal at `phase`, between the samples around it, wrapping from the loop's end to its start
+    fn goal_at(&self, phase: Duration) -> Option<Vec2> {
+        goal_at(&self.record, self.loop_length, self.interpolation, phase)
+    }
+
+    // actions after `from` and up to `to`, in the order they happened
+    fn actions_due(&self, from: Duration, to: Duration) -> Vec<TimedAction> {
+        if from <= to {
+            self.actions.iter()
+                .filter(|(time, ..)| *time > from && *time <= to)
+                .copied()
+                .collect()
+        } else {
+            // the end of this loop, then the start of the next
+            self.actions.iter()
+                .filter(|(time, ..)| *time > from)
+                .chain(self.actions.iter().filter(|(time, ..)| *time <= to))
+                .copied()
+                .collect()
+        }
+    }
 }
 
-const RECORDING_TIME: u64 = 3;
+// the goal at `phase` of a phase-sorted `record`, between the samples around it, wrapping from the
+// loop's end to its start
+fn goal_at(record: &[(Duration, Vec2)], loop_length: Duration, interpolation: Interpolation, phase: Duration) -> Option<Vec2> {
+    let count = record.len();
+    let (&(first_time, first), &(last_time, _)) = (record.first()?, record.last()?);
+
+    if count == 1 {
+        return Some(first);
+    }
+
+    let loop_length = loop_length.as_secs_f32();
+    let phase = phase.as_secs_f32();
+
+    // index of the sample after `phase`, past the end it's the first one of the next loop
+    let next = record.partition_point(|(time, _)| time.as_secs_f32() <= phase);
+
+    let (from_time, to_time) = match next {
+        0 => (last_time.as_secs_f32() - loop_length, first_time.as_secs_f32()),
+        next if next == count => (last_time.as_secs_f32(), first_time.as_secs_f32() + loop_length),
+        next => (record[next - 1].0.as_secs_f32(), record[next].0.as_secs_f32()),
+    };
+
+    let from = (next + count - 1) % count;
+    let to = next % count;
+
+    let t = if to_time > from_time { ((phase - from_time) / (to_time - from_time)).clamp(0.0, 1.0) } else { 0.0 };
+    let point = |index: usize| record[index % count].1;
+
+    Some(match interpolation {
+        Interpolation::Linear => point(from).lerp(point(to), t),
+        Interpolation::CatmullRom => catmull_rom(
+            point(from + count - 1),
+            point(from),
+            point(to),
+            point(to + 1),
+            t,
+        ),
+    })
+}
+
+// uniform Catmull-Rom spline through `p1` and `p2`
+fn catmull_rom(p0: Vec2, p1: Vec2, p2: Vec2, p3: Vec2, t: f32) -> Vec2 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+
+    0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+// Drops samples that a straight line between the samples kept around them already passes
+// within `COMPRESS_TOLERANCE` of, so a hand that holds still or moves steadily costs two samples.
+// A curve through the kept samples can swing wide of the dropped ones at corners, so the path is
+// then played back with `interpolation` and any sample it misses is put back until none are.
+fn compress(record: &[(Duration, Vec2)], loop_length: Duration, interpolation: Interpolation) -> Vec<(Duration, Vec2)> {
+    if record.len() <= 2 {
+        return record.to_vec();
+    }
+
+    let mut keep = vec![false; record.len()];
+    keep[0] = true;
+    keep[record.len() - 1] = true;
+
+    let mut anchor = 0;
+
+    for end in 2..record.len() {
+        let (start_time, start) = record[anchor];
+        let (end_time, end_position) = record[end];
+        let span = (end_time - start_time).as_secs_f32();
+
+        let fits = record[anchor + 1..end].iter().all(|(time, position)| {
+            let t = if span > 0.0 { (*time - start_time).as_secs_f32() / span } else { 0.0 };
+
+            start.lerp(end_position, t).distance(*position) <= COMPRESS_TOLERANCE
+        });
+
+        if !fits {
+            anchor = end - 1;
+            keep[anchor] = true;
+        }
+    }
+
+    loop {
+        let kept: Vec<(Duration, Vec2)> = record.iter().zip(&keep)
+            .filter(|(_, keep)| **keep)
+            .map(|(sample, _)| *sample)
+            .collect();
+
+        let missed: Vec<usize> = (0..record.len())
+            .filter(|index| !keep[*index])
+            .filter(|index| {
+                let (time, position) = record[*index];
+
+                goal_at(&kept, loop_length, interpolation, time)
+                    .map_or(true, |goal| goal.distance(position) > COMPRESS_TOLERANCE)
+            })
+            .collect();
+
+        if missed.is_empty() {
+            return kept;
+        }
+
+        for index in missed {
+            keep[index] = true;
+        }
+    }
+}
+
+/// Time since the level started, shared by every recording and ghost hand.
+#[derive(Resource, Default)]
+pub struct LoopClock {
+    elapsed: Duration,
+}
+
+impl LoopClock {
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// How far into a loop of `length` the clock is.
+    pub fn phase(&self, length: Duration) -> Duration {
+        if length.is_zero() {
+            return Duration::ZERO;
+        }
+
+        Duration::from_nanos((self.elapsed.as_nanos() % length.as_nanos()) as u64)
+    }
+
+    /// When the next loop of `length` starts, now if one starts right now.
+    pub fn next_loop(&self, length: Duration) -> Duration {
+        let phase = self.phase(length);
+
+        if phase.is_zero() {
+            self.elapsed
+        } else {
+            self.elapsed + (length - phase)
+        }
+    }
+}
+
+/// Makes new recordings wait for the level's next loop to start instead of starting right away.
+#[derive(Resource, Default)]
+pub struct WaitForLoop(pub bool);
+
+/// Loop length in seconds for levels that don't set `loop_length`.
+pub const DEFAULT_LOOP_LENGTH: f32 = 3.0;
+
+/// How long the current level's loop is, recordings last this long unless a different
+/// [`LoopChoice`] is picked.
+#[derive(Resource)]
+pub struct LoopLength(pub Duration);
+
+impl Default for LoopLength {
+    fn default() -> Self {
+        Self(Duration::from_secs_f32(DEFAULT_LOOP_LENGTH))
+    }
+}
+
+/// Length of the next recording compared to the level's loop.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopChoice {
+    /// half the level's loop
+    Short,
+    #[default]
+    Medium,
+    /// twice the level's loop
+    Long,
+}
+
+impl LoopChoice {
+    pub fn length(self, level: Duration) -> Duration {
+        match self {
+            LoopChoice::Short => level / 2,
+            LoopChoice::Medium => level,
+            LoopChoice::Long => level * 2,
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            LoopChoice::Short => LoopChoice::Medium,
+            LoopChoice::Medium => LoopChoice::Long,
+            LoopChoice::Long => LoopChoice::Short,
+        }
+    }
+}
 
 pub(super) fn register(app: &mut App) {
     app
-        .add_systems(Update, (record, playback))
-        .add_systems(FixedUpdate, recording);
+        .init_resource::<LoopLength>()
+        .init_resource::<LoopChoice>()
+        .init_resource::<LoopClock>()
+        .init_resource::<WaitForLoop>()
+        .init_resource::<GhostMotion>()
+        .add_systems(Update, (choose_loop, toggle_wait, record).run_if(in_play));
+}
+
+// runs on fixed ticks, so the clock only ever lands on whole ticks
+pub(super) fn tick_clock(
+    mut clock: ResMut<LoopClock>,
+    time: Res<Time>,
+) {
+    clock.elapsed += time.delta();
+}
+
+fn toggle_wait(
+    hands: Query<&ActionState<HandActions>, With<CurrentHand>>,
+    mut wait: ResMut<WaitForLoop>,
+) {
+    let Ok(action) = hands.get_single() else {
+        return;
+    };
+
+    if action.just_pressed(&HandActions::WaitForLoop) {
+        wait.0 = !wait.0;
+        info!("recordings start {}", if wait.0 { "at the next loop" } else { "right away" });
+    }
+}
+
+fn choose_loop(
+    hands: Query<&ActionState<HandActions>, With<CurrentHand>>,
+    loop_length: Res<LoopLength>,
+    mut choice: ResMut<LoopChoice>,
+) {
+    let Ok(action) = hands.get_single() else {
+        return;
+    };
+
+    if action.just_pressed(&HandActions::CycleLoop) {
+        *choice = choice.next();
+        info!("next recording: {:?} loop, {:.1}s", *choice, choice.length(loop_length.0).as_secs_f32());
+    }
 }
 
 fn record (
     mut commands: Commands,
-    hands: Query<(&ActionState<HandActions>, Entity), With<CurrentHand>>,
+    hands: Query<(&ActionState<HandActions>, Entity, Has<Recording>), With<CurrentHand>>,
+    loop_length: Res<LoopLength>,
+    choice: Res<LoopChoice>,
+    clock: Res<LoopClock>,
+    wait: Res<WaitForLoop>,
+    ghosts: Query<(), With<Playback>>,
+    limit: Res<GhostLimit>,
+    selected: Res<SelectedGhost>,
 ) {
-    let (action, entity) = hands.single();
+    let Ok((action, entity, recording)) = hands.get_single() else {
+        return;
+    };
     
-    //TODO! disallow for more than one recording at once
     if !action.just_pressed(&HandActions::Record) {
         return;
     }
 
-    //TODO! add some sort of indincator that you are recording
-    println!("AH");
+    // a second press cancels, only one recording runs at a time
+    if recording {
+        commands.entity(entity).remove::<Recording>();
+        info!("recording cancelled");
+        return;
+    }
+
+    // re-recording a ghost doesn't add one
+    let ghost_count = ghosts.iter().count().saturating_sub(usize::from(selected.0.is_some()));
+
+    if !limit.allows(ghost_count) {
+        info!("this level allows {} ghost hands, delete one to record another", limit.0.unwrap_or_default());
+        return;
+    }
 
     let mut entity_commands = commands.entity(entity);
 
     entity_commands.insert(Recording {
-        timer: Timer::new(Duration::from_secs(RECORDING_TIME), TimerMode::Once),
+        start: if wait.0 { clock.next_loop(loop_length.0) } else { clock.elapsed() },
+        length: choice.length(loop_length.0),
         record: Vec::new(),
-        grabs: Vec::new(),
+        actions: Vec::new(),
+        throws: Vec::new(),
+        replaces: selected.0,
     });
 }
 
-fn recording (
+pub(super) fn recording (
     mut commands: Commands,
-    mut hands: Query<(&mut Recording, Entity, &Goal)>,
-    time: Res<Time>,
+    mut hands: Query<(&mut Recording, Entity, &Goal, &HandInput, &HandDefinition)>,
+    ghosts: Query<Option<&Grabbing>, With<Playback>>,
+    joints: Query<&RevoluteJoint>,
+    clock: Res<LoopClock>,
+    ghost_motion: Res<GhostMotion>,
     asset_server: Res<AssetServer>,
 ) {
-    for (mut recording, entity, goal) in hands.iter_mut() {
-        recording.timer.tick(time.delta());
-
-        let elasped = recording.timer.elapsed();
-        let goal_position = goal.0;
-
-        recording.record.push((elasped, goal_position));
-
-        if recording.timer.finished() {
-            let mut entity_commands = commands.entity(entity);
-            entity_commands.remove::<Recording>();
-
-            let mut record = recording.record.clone();
+    for (mut recording, entity, goal, input, definition) in hands.iter_mut() {
+        match recording.state(&clock) {
+            RecordingState::Countdown => continue,
+            RecordingState::Recording => {
+                let phase = clock.phase(recording.length);
+                recording.record.push((phase, goal.0));
+                recording.actions.extend(input.tick().iter().map(|action| (phase, *action)));
+                recording.throws.extend(input.throw().map(|velocity| (phase, velocity)));
+                continue;
+            }
+            RecordingState::Committing => {}
+        }
 
-            record.reverse();
+        let mut entity_commands = commands.entity(entity);
+        entity_commands.remove::<Recording>();
 
-            let mut grabs = recording.grabs.clone();
+        if recording.record.is_empty() {
+            warn!("recording ended without any samples, no ghost hand spawned");
+            continue;
+        }
 
-            grabs.reverse();
+        if let Some((ghost, grabbing)) = recording.replaces.and_then(|ghost| Some((ghost, ghosts.get(ghost).ok()?))) {
+            despawn_ghost(&mut commands, ghost, grabbing, &joints);
+        }
 
-            let texture = asset_server.load("hand.png");
+        // each ghost keeps the length it was recorded with
+        let playback = Playback::new(
+            recording.length,
+            std::mem::take(&mut recording.record),
+            std::mem::take(&mut recording.actions),
+            std::mem::take(&mut recording.throws),
+            ghost_motion.0,
+        );
 
-            // Spawns the hand!
-            commands.spawn((
-                HandBundle::default(),
-                SpriteBundle {
-                    texture,
-                    transform: Transform::from_scale(Vec3::new(0.5, 0.5, 0.5)),
-                    ..default()
-                },
-                ActionState::<HandActions>::default(),
-                Playback {
-                    timer: Timer::new(Duration::from_secs(RECORDING_TIME), TimerMode::Repeating),
-                    record: record.clone(),
-                    stored_record: record.clone(),
-                    grabs: grabs.clone(),
-                    stored_grabs: grabs.clone(),
-                },
-            )).with_children(|parent| {
-                parent.spawn((
-                    TransformBundle::from_transform(Transform::from_xyz(0.0, -HAND_OFFSET, 0.0)),
-                    Collider::rectangle(400.0, 600.0),
-                    Sensor,
-                ));
-            });
-        }
+        // ghosts look and reach like the hand that recorded them
+        spawn_ghost(&mut commands, &asset_server, definition, playback);
     }
 }
 
-fn playback (
-    mut hands: Query<(&mut Playback, &mut Goal, &mut ActionState<HandActions>)>,
-    time: Res<Time>,
+/// Spawns a ghost hand that repeats `playback` forever.
+pub(super) fn spawn_ghost(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    definition: &HandDefinition,
+    playback: Playback,
 ) {
-    for (mut playback, mut goal, mut action) in hands.iter_mut() {
-        playback.timer.tick(time.delta());
+    definition.spawn(commands, asset_server).insert(playback);
+}
 
-        if playback.timer.finished() {
-            playback.record = playback.stored_record.clone();
-            playback.grabs = playback.stored_grabs.clone();
+pub(super) fn playback (
+    mut hands: Query<(&mut Playback, &mut Goal, &mut HandInput, Has<Muted>)>,
+    clock: Res<LoopClock>,
+) {
+    for (mut playback, mut goal, mut input, muted) in hands.iter_mut() {
+        // pick the loop back up where the clock is once unmuted, without the actions missed meanwhile
+        if muted {
+            playback.last_phase = None;
+            continue;
         }
 
-        let (next_time, goal_position) = *playback.record.last().unwrap();
+        let phase = clock.phase(playback.loop_length);
 
-        if playback.timer.elapsed() >= next_time {
+        if let Some(goal_position) = playback.goal_at(phase) {
             *goal = Goal(goal_position);
-            playback.record.pop();
         }
 
-        if action.pressed(&HandActions::Grab) {
-            action.release(&HandActions::Grab);
+        if let Some(last_phase) = playback.last_phase {
+            for (time, action) in playback.actions_due(last_phase, phase) {
+                match playback.throw_at(time) {
+                    Some(velocity) if action == HandActions::Grab => input.queue_throw(velocity),
+                    _ => input.queue(action),
+                }
+            }
         }
 
-        if playback.grabs.is_empty() {
-            continue;
+        playback.last_phase = Some(phase);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(time: f32) -> Duration {
+        Duration::from_secs_f32(time)
+    }
+
+    // a hand that moves right for a second, then up for a second, sampled every tick
+    fn corner() -> Vec<(Duration, Vec2)> {
+        (0..128)
+            .map(|tick| {
+                let time = tick as f32 / 64.0;
+                let position = if time <= 1.0 { Vec2::new(time * 200.0, 0.0) } else { Vec2::new(200.0, (time - 1.0) * 200.0) };
+
+                (secs(time), position)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn clock_phase_and_next_loop() {
+        let clock = LoopClock { elapsed: secs(7.0) };
+
+        assert_eq!(clock.phase(secs(3.0)), secs(1.0));
+        assert_eq!(clock.phase(Duration::ZERO), Duration::ZERO);
+        assert_eq!(clock.next_loop(secs(3.0)), secs(9.0));
+        assert_eq!(clock.next_loop(secs(7.0)), secs(7.0));
+    }
+
+    #[test]
+    fn playback_wraps_samples_into_the_loop() {
+        let record = vec![(secs(0.5), Vec2::ZERO), (secs(3.5), Vec2::new(100.0, 0.0))];
+        let actions = vec![(secs(2.5), HandActions::Grab)];
+        let throws = vec![(secs(2.5), Vec2::new(300.0, 0.0))];
+        let playback = Playback::new(secs(2.0), record, actions, throws, Interpolation::Linear);
+
+        assert_eq!(playback.record().map(|(time, _)| *time).collect::<Vec<_>>(), [secs(0.5), secs(1.5)]);
+        assert_eq!(playback.actions().collect::<Vec<_>>(), [&(secs(0.5), HandActions::Grab)]);
+        assert_eq!(playback.throw_at(secs(0.5)), Some(Vec2::new(300.0, 0.0)));
+    }
+
+    #[test]
+    fn actions_due_across_the_end_of_the_loop() {
+        let actions = [secs(0.1), secs(1.0), secs(1.95)].map(|time| (time, HandActions::Grab)).to_vec();
+        let playback = Playback::new(secs(2.0), Vec::new(), actions, Vec::new(), Interpolation::Linear);
+        let due = |from, to| playback.actions_due(secs(from), secs(to)).iter().map(|(time, _)| *time).collect::<Vec<_>>();
+
+        assert_eq!(due(0.5, 1.0), [secs(1.0)]);
+        assert!(due(1.0, 1.5).is_empty());
+        // the end of the loop comes before the start of the next
+        assert_eq!(due(1.9, 0.2), [secs(1.95), secs(0.1)]);
+        assert_eq!(due(0.0, 1.99).len(), 3);
+    }
+
+    #[test]
+    fn goal_at_interpolates_linearly() {
+        let record = [(secs(0.0), Vec2::ZERO), (secs(1.0), Vec2::new(100.0, 0.0))];
+        let goal = |phase| goal_at(&record, secs(2.0), Interpolation::Linear, secs(phase)).unwrap();
+
+        assert_eq!(goal(0.0), Vec2::ZERO);
+        assert_eq!(goal(0.5), Vec2::new(50.0, 0.0));
+        assert_eq!(goal(1.0), Vec2::new(100.0, 0.0));
+        // heads back to the first sample over the rest of the loop
+        assert_eq!(goal(1.5), Vec2::new(50.0, 0.0));
+    }
+
+    #[test]
+    fn goal_at_wraps_before_the_first_sample() {
+        let record = [(secs(1.0), Vec2::ZERO), (secs(3.0), Vec2::new(100.0, 0.0))];
+
+        let goal = goal_at(&record, secs(4.0), Interpolation::Linear, secs(0.0)).unwrap();
+
+        assert!(goal.distance(Vec2::new(50.0, 0.0)) < 1e-3);
+    }
+
+    #[test]
+    fn goal_at_with_few_samples() {
+        let one = [(secs(1.0), Vec2::new(10.0, 20.0))];
+
+        assert_eq!(goal_at(&[], secs(2.0), Interpolation::Linear, secs(0.5)), None);
+        assert_eq!(goal_at(&one, secs(2.0), Interpolation::CatmullRom, secs(0.5)), Some(Vec2::new(10.0, 20.0)));
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_samples() {
+        let record: Vec<_> = corner().into_iter().step_by(16).collect();
+
+        for (time, position) in record.iter() {
+            let goal = goal_at(&record, secs(2.0), Interpolation::CatmullRom, *time).unwrap();
+
+            assert!(goal.distance(*position) < 1e-3);
         }
+    }
 
-        let next_grab= *playback.grabs.last().unwrap();
+    #[test]
+    fn compress_keeps_the_ends_of_a_straight_line() {
+        let still: Vec<_> = (0..64).map(|tick| (secs(tick as f32 / 64.0), Vec2::new(5.0, 5.0))).collect();
+        let line: Vec<_> = corner().into_iter().take(64).collect();
 
-        if playback.timer.elapsed() >= next_grab {
-            action.press(&HandActions::Grab);
-            playback.grabs.pop();
-            continue;
+        assert_eq!(compress(&still, secs(2.0), Interpolation::Linear), [still[0], still[63]]);
+        assert_eq!(compress(&line, secs(2.0), Interpolation::Linear), [line[0], line[63]]);
+    }
+
+    #[test]
+    fn compress_keeps_corners() {
+        let record = corner();
+        let compressed = compress(&record, secs(2.0), Interpolation::Linear);
+
+        assert!(compressed.len() < 8);
+        assert!(compressed.iter().any(|(_, position)| position.distance(Vec2::new(200.0, 0.0)) < 5.0));
+    }
+
+    #[test]
+    fn compressed_path_plays_back_within_tolerance() {
+        let record = corner();
+
+        for interpolation in [Interpolation::Linear, Interpolation::CatmullRom] {
+            let compressed = compress(&record, secs(2.0), interpolation);
+
+            for (time, position) in record.iter() {
+                let goal = goal_at(&compressed, secs(2.0), interpolation, *time).unwrap();
+
+                assert!(goal.distance(*position) <= COMPRESS_TOLERANCE, "{:?} missed {} at {:?}", interpolation, position, time);
+            }
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,465 @@
+use std::fmt;
+use std::time::Duration;
+
+use bevy::asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadState};
+use bevy::reflect::TypePath;
+use level::CurrentLevel;
+use state::in_play;
+use serde::{Deserialize, Serialize};
+
+use super::*;
+use recording::spawn_ghost;
+
+/// Bumped whenever the layout of [`SavedRecording`] changes.
+pub const RECORDING_VERSION: u32 = 4;
+
+/// A ghost hand's loop as written to a `.recording.toml` file, times are in seconds.
+#[derive(Serialize, Deserialize, Asset, TypePath, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SavedRecording {
+    pub version: u32,
+    pub level: String,
+    pub loop_length: f32,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    // [time, x, y], only the samples needed to rebuild the path
+    pub goals: Vec<[f32; 3]>,
+    pub actions: Vec<SavedAction>,
+    // [time, x, y] of the velocity each throw let go with, at the time of its grab press
+    #[serde(default)]
+    pub throws: Vec<[f32; 3]>,
+}
+
+/// A press of one of the hand's actions, `{ time = 0.5, action = "Grab" }`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SavedAction {
+    pub time: f32,
+    pub action: HandActions,
+}
+
+/// Why a recording file can't be played back.
+#[derive(Debug, Clone)]
+pub struct RecordingError(pub String);
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for RecordingError {}
+
+impl SavedRecording {
+    pub fn parse(text: &str) -> Result<Self, RecordingError> {
+        let recording: Self = toml::from_str(text).map_err(|e| RecordingError(e.message().to_string()))?;
+        recording.validate()?;
+
+        Ok(recording)
+    }
+
+    /// Checks the numbers a hand-edited or broken file could get wrong, a negative or NaN time
+    /// can't become a [`Duration`].
+    pub fn validate(&self) -> Result<(), RecordingError> {
+        if !(self.loop_length > 0.0 && self.loop_length.is_finite()) {
+            return Err(RecordingError(format!(
+                "loop_length: expected a positive number of seconds, found {}", self.loop_length
+            )));
+        }
+
+        let times = self.goals.iter().map(|[time, ..]| ("goals", *time))
+            .chain(self.actions.iter().map(|saved| ("actions", saved.time)))
+            .chain(self.throws.iter().map(|[time, ..]| ("throws", *time)));
+
+        for (field, time) in times {
+            if !(0.0..=self.loop_length).contains(&time) {
+                return Err(RecordingError(format!(
+                    "{}: time {} is outside the loop, expected 0 to {}", field, time, self.loop_length
+                )));
+            }
+        }
+
+        let points = self.goals.iter().map(|[_, x, y]| ("goals", x, y))
+            .chain(self.throws.iter().map(|[_, x, y]| ("throws", x, y)));
+
+        for (field, x, y) in points {
+            if !(x.is_finite() && y.is_finite()) {
+                return Err(RecordingError(format!("{}: expected numbers, found [{}, {}]", field, x, y)));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn from_playback(level: &str, playback: &Playback) -> Self {
+        Self {
+            version: RECORDING_VERSION,
+            level: level.to_string(),
+            loop_length: playback.loop_length().as_secs_f32(),
+            interpolation: playback.interpolation(),
+            goals: playback.record()
+                .map(|(time, goal)| [time.as_secs_f32(), goal.x, goal.y])
+                .collect(),
+            actions: playback.actions()
+                .map(|(time, action)| SavedAction {
+                    time: time.as_secs_f32(),
+                    action: *action,
+                })
+                .collect(),
+            throws: playback.throws()
+                .map(|(time, velocity)| [time.as_secs_f32(), velocity.x, velocity.y])
+                .collect(),
+        }
+    }
+
+    pub fn to_playback(&self) -> Result<Playback, RecordingError> {
+        let seconds = |time: f32| Duration::try_from_secs_f32(time)
+            .map_err(|_| RecordingError(format!("{} is not a time in seconds", time)));
+
+        Ok(Playback::new(
+            seconds(self.loop_length)?,
+            self.goals.iter()
+                .map(|[time, x, y]| Ok((seconds(*time)?, Vec2::new(*x, *y))))
+                .collect::<Result<_, RecordingError>>()?,
+            self.actions.iter()
+                .map(|saved| Ok((seconds(saved.time)?, saved.action)))
+                .collect::<Result<_, RecordingError>>()?,
+            self.throws.iter()
+                .map(|[time, x, y]| Ok((seconds(*time)?, Vec2::new(*x, *y))))
+                .collect::<Result<_, RecordingError>>()?,
+            self.interpolation,
+        ))
+    }
+}
+
+#[derive(Default)]
+struct RecordingLoader;
+
+impl AssetLoader for RecordingLoader {
+    type Asset = SavedRecording;
+    type Settings = ();
+    type Error = RecordingError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<SavedRecording, RecordingError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text).await
+            .map_err(|e| RecordingError(e.to_string()))?;
+
+        SavedRecording::parse(&text)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["recording.toml"]
+    }
+}
+
+/// Spawns a ghost hand from the recording at this asset path once it has loaded.
+#[derive(Event)]
+pub struct ImportRecording(pub String);
+
+#[derive(Resource, Default)]
+struct PendingRecordings {
+    recordings: Vec<Handle<SavedRecording>>,
+    // read straight from the recordings folder, the asset server would hand back what it
+    // cached before the last export
+    read: Vec<SavedRecording>,
+}
+
+pub(super) fn register(app: &mut App) {
+    app
+        .init_asset::<SavedRecording>()
+        .init_asset_loader::<RecordingLoader>()
+        .init_resource::<PendingRecordings>()
+        .add_event::<ImportRecording>()
+        .add_systems(Update, (export.run_if(in_play), import.run_if(in_play), import_event, spawn_imported).chain());
+}
+
+fn recordings_folder(level: &str) -> String {
+    format!("recordings/{}", level)
+}
+
+fn export(
+    hands: Query<&ActionState<HandActions>, With<CurrentHand>>,
+    ghosts: Query<&Playback>,
+    current_level: Res<CurrentLevel>,
+) {
+    let Ok(action) = hands.get_single() else {
+        return;
+    };
+
+    if !action.just_pressed(&HandActions::Export) {
+        return;
+    }
+
+    let Some(level) = &current_level.0 else {
+        return;
+    };
+
+    let recordings: Vec<SavedRecording> = ghosts.iter()
+        .map(|playback| SavedRecording::from_playback(level, playback))
+        .collect();
+
+    write_recordings(&recordings_folder(level), &recordings);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn recordings_path(folder: &str) -> std::path::PathBuf {
+    use bevy::asset::io::file::FileAssetReader;
+
+    FileAssetReader::get_base_path().join("assets").join(folder)
+}
+
+// the `.recording.toml` files in `folder`, in name order
+#[cfg(not(target_arch = "wasm32"))]
+fn recording_files(folder: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let Ok(entries) = std::fs::read_dir(folder) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<_> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.ends_with(".recording.toml")))
+        .collect();
+
+    files.sort();
+    files
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_recordings(folder: &str, recordings: &[SavedRecording]) {
+    let folder = recordings_path(folder);
+
+    if let Err(e) = std::fs::create_dir_all(&folder) {
+        error!("could not create {}: {}", folder.display(), e);
+        return;
+    }
+
+    // the folder holds just this export, or an import would bring back ghosts deleted since
+    for path in recording_files(&folder) {
+        if let Err(e) = std::fs::remove_file(&path) {
+            error!("could not remove the old recording {}: {}", path.display(), e);
+            return;
+        }
+    }
+
+    for (index, recording) in recordings.iter().enumerate() {
+        let path = folder.join(format!("{}.recording.toml", index));
+
+        let result = toml::to_string(recording)
+            .map_err(|e| e.to_string())
+            .and_then(|text| std::fs::write(&path, text).map_err(|e| e.to_string()));
+
+        match result {
+            Ok(()) => info!("saved recording to {}", path.display()),
+            Err(e) => error!("could not save recording to {}: {}", path.display(), e),
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_recordings(_folder: &str, _recordings: &[SavedRecording]) {
+    warn!("saving recordings isn't supported on the web");
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_recordings(folder: &str) -> Vec<SavedRecording> {
+    recording_files(&recordings_path(folder)).into_iter()
+        .filter_map(|path| {
+            let result = std::fs::read_to_string(&path)
+                .map_err(|e| RecordingError(e.to_string()))
+                .and_then(|text| SavedRecording::parse(&text));
+
+            match result {
+                Ok(recording) => Some(recording),
+                Err(e) => {
+                    error!("could not read recording {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read_recordings(_folder: &str) -> Vec<SavedRecording> {
+    warn!("loading recordings isn't supported on the web");
+    Vec::new()
+}
+
+fn import(
+    hands: Query<&ActionState<HandActions>, With<CurrentHand>>,
+    current_level: Res<CurrentLevel>,
+    mut pending: ResMut<PendingRecordings>,
+) {
+    let Ok(action) = hands.get_single() else {
+        return;
+    };
+
+    if !action.just_pressed(&HandActions::Import) {
+        return;
+    }
+
+    if let Some(level) = &current_level.0 {
+        let recordings = read_recordings(&recordings_folder(level));
+        pending.read.extend(recordings);
+    }
+}
+
+fn import_event(
+    mut ev_import: EventReader<ImportRecording>,
+    asset_server: Res<AssetServer>,
+    mut pending: ResMut<PendingRecordings>,
+) {
+    for ev in ev_import.read() {
+        pending.recordings.push(asset_server.load(ev.0.clone()));
+    }
+}
+
+fn spawn_imported(
+    mut commands: Commands,
+    mut pending: ResMut<PendingRecordings>,
+    recordings: Res<Assets<SavedRecording>>,
+    current_level: Res<CurrentLevel>,
+    asset_server: Res<AssetServer>,
+    ghosts: Query<(), With<Playback>>,
+    limit: Res<GhostLimit>,
+    hand: Res<HandHandle>,
+    definitions: Res<Assets<HandDefinition>>,
+) {
+    // ghost hands can't be spawned until there's a hand to spawn them from
+    let Some(definition) = definitions.get(&hand.0) else {
+        return;
+    };
+
+    let pending = &mut *pending;
+    let mut ready = std::mem::take(&mut pending.read);
+
+    pending.recordings.retain(|handle| {
+        let Some(recording) = recordings.get(handle) else {
+            // keep waiting unless it failed to load
+            return !matches!(asset_server.load_state(handle.id()), LoadState::Failed(_));
+        };
+
+        ready.push(recording.clone());
+        false
+    });
+
+    let mut ghost_count = ghosts.iter().count();
+
+    for recording in ready {
+        if recording.version != RECORDING_VERSION {
+            warn!(
+                "skipping recording made for version {}, this game reads version {}",
+                recording.version, RECORDING_VERSION,
+            );
+            continue;
+        }
+
+        if recording.goals.is_empty() {
+            warn!("skipping recording with no goal positions");
+            continue;
+        }
+
+        if current_level.0.as_ref() != Some(&recording.level) {
+            warn!("skipping recording made for level {}", recording.level);
+            continue;
+        }
+
+        if !limit.allows(ghost_count) {
+            warn!("skipping recording, this level allows {} ghost hands", limit.0.unwrap_or_default());
+            continue;
+        }
+
+        match recording.to_playback() {
+            Ok(playback) => {
+                spawn_ghost(&mut commands, &asset_server, definition, playback);
+                ghost_count += 1;
+            }
+            Err(e) => error!("skipping recording: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grab(time: f32) -> SavedAction {
+        SavedAction { time, action: HandActions::Grab }
+    }
+
+    fn recording() -> SavedRecording {
+        SavedRecording {
+            version: RECORDING_VERSION,
+            level: "wall".to_string(),
+            loop_length: 3.0,
+            interpolation: Interpolation::CatmullRom,
+            goals: vec![[0.0, 0.0, 0.0], [0.5, 100.0, 0.0], [1.5, 100.0, 50.0]],
+            actions: vec![grab(0.25), grab(1.25)],
+            throws: vec![[1.25, 300.0, -40.0]],
+        }
+    }
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let text = toml::to_string(&recording()).unwrap();
+        let loaded = SavedRecording::parse(&text).unwrap();
+        let saved = SavedRecording::from_playback(&loaded.level, &loaded.to_playback().unwrap());
+
+        assert_eq!(saved.loop_length, 3.0);
+        assert_eq!(saved.interpolation, Interpolation::CatmullRom);
+        assert_eq!(saved.goals, recording().goals);
+        assert_eq!(saved.actions, recording().actions);
+        assert_eq!(saved.throws, recording().throws);
+        assert_eq!(toml::to_string(&saved).unwrap(), text);
+    }
+
+    #[test]
+    fn rejects_a_bad_loop_length() {
+        for loop_length in [0.0, -3.0, f32::NAN, f32::INFINITY] {
+            let recording = SavedRecording { loop_length, ..recording() };
+
+            assert!(recording.validate().is_err(), "accepted loop_length = {}", loop_length);
+        }
+    }
+
+    #[test]
+    fn rejects_times_outside_the_loop() {
+        let late_goal = SavedRecording { goals: vec![[3.5, 0.0, 0.0]], ..recording() };
+        let early_grab = SavedRecording { actions: vec![grab(-0.5)], ..recording() };
+        let missing_time = SavedRecording { actions: vec![grab(f32::NAN)], ..recording() };
+
+        assert!(late_goal.validate().unwrap_err().0.starts_with("goals"));
+        assert!(early_grab.validate().unwrap_err().0.starts_with("actions"));
+        assert!(missing_time.validate().is_err());
+    }
+
+    #[test]
+    fn rejects_points_that_are_not_numbers() {
+        let goal = SavedRecording { goals: vec![[1.0, f32::INFINITY, 0.0]], ..recording() };
+        let throw = SavedRecording { throws: vec![[1.25, 0.0, f32::NAN]], ..recording() };
+
+        assert!(goal.validate().unwrap_err().0.starts_with("goals"));
+        assert!(throw.validate().unwrap_err().0.starts_with("throws"));
+    }
+
+    #[test]
+    fn parse_rejects_unknown_fields() {
+        let text = toml::to_string(&recording()).unwrap() + "speed = 2.0\n";
+
+        assert!(SavedRecording::parse(&text).is_err());
+    }
+
+    #[test]
+    fn interpolation_defaults_to_linear() {
+        let text = toml::to_string(&recording()).unwrap().replace("interpolation = \"catmull_rom\"\n", "");
+        let loaded = SavedRecording::parse(&text).unwrap();
+
+        assert_eq!(loaded.interpolation, Interpolation::Linear);
+    }
+}
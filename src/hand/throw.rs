@@ -0,0 +1,33 @@
+use std::collections::VecDeque;
+
+use super::*;
+
+/// How many ticks of the hand's motion a throw averages, enough to smooth out a jittery mouse.
+const THROW_SAMPLES: usize = 6;
+
+/// The hand's velocity over its last few ticks, an object it lets go of leaves with their average.
+#[derive(Component, Default)]
+pub struct VelocityHistory(VecDeque<Vec2>);
+
+impl VelocityHistory {
+    pub fn average(&self) -> Vec2 {
+        if self.0.is_empty() {
+            return Vec2::ZERO;
+        }
+
+        self.0.iter().sum::<Vec2>() / self.0.len() as f32
+    }
+}
+
+// after `move_hand`, so the newest sample is the velocity the hand moves with this tick
+pub(super) fn sample_velocity(
+    mut hands: Query<(&LinearVelocity, &mut VelocityHistory), With<Hand>>,
+) {
+    for (velocity, mut history) in hands.iter_mut() {
+        if history.0.len() == THROW_SAMPLES {
+            history.0.pop_front();
+        }
+
+        history.0.push_back(velocity.0);
+    }
+}
@@ -0,0 +1,194 @@
+use hand::{Bindings, GhostLimit, HandActions, LoopChoice, LoopClock, LoopLength, Playback, Recording, RecordingState, SelectedGhost, WaitForLoop};
+use state::GameState;
+use submit::Deliveries;
+
+use super::*;
+
+const BAR_WIDTH: f32 = 300.0;
+
+const RECORDING_COLOR: Color = Color::srgb(0.9, 0.15, 0.15);
+const WAITING_COLOR: Color = Color::srgb(0.9, 0.6, 0.1);
+const LOOP_COLOR: Color = Color::srgb(0.8, 0.8, 0.8);
+const TRAIL_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.15);
+const SELECTED_TRAIL_COLOR: Color = Color::srgba(1.0, 0.85, 0.3, 0.5);
+
+#[derive(Component)]
+struct Hud;
+
+#[derive(Component)]
+struct HudText;
+
+#[derive(Component)]
+struct LoopBar;
+
+pub(super) fn register(app: &mut App) {
+    app
+        .add_systems(Startup, setup)
+        .add_systems(Update, (show_hud, (update_text, update_bar, draw_trails).run_if(in_level)));
+}
+
+// the HUD stays up behind the pause and level complete screens
+fn in_level(state: Res<State<GameState>>) -> bool {
+    matches!(state.get(), GameState::Playing | GameState::Paused | GameState::LevelComplete)
+}
+
+fn setup(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.0),
+                left: Val::Px(10.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(6.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                ..default()
+            },
+            background_color: Color::srgba(0.0, 0.0, 0.0, 0.5).into(),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        Hud,
+    )).with_children(|parent| {
+        parent.spawn((
+            TextBundle::from_section("", TextStyle {
+                font_size: 20.0,
+                color: Color::WHITE,
+                ..default()
+            }),
+            HudText,
+        ));
+
+        // loop progress
+        parent.spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(BAR_WIDTH),
+                height: Val::Px(8.0),
+                ..default()
+            },
+            background_color: Color::srgba(1.0, 1.0, 1.0, 0.2).into(),
+            ..default()
+        }).with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Px(0.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    background_color: LOOP_COLOR.into(),
+                    ..default()
+                },
+                LoopBar,
+            ));
+        });
+    });
+}
+
+fn show_hud(
+    mut huds: Query<&mut Visibility, With<Hud>>,
+    state: Res<State<GameState>>,
+) {
+    let visibility = if in_level(state) { Visibility::Inherited } else { Visibility::Hidden };
+
+    for mut hud in huds.iter_mut() {
+        if *hud != visibility {
+            *hud = visibility;
+        }
+    }
+}
+
+fn update_text(
+    mut texts: Query<&mut Text, With<HudText>>,
+    recordings: Query<&Recording>,
+    ghosts: Query<(), With<Playback>>,
+    clock: Res<LoopClock>,
+    loop_length: Res<LoopLength>,
+    choice: Res<LoopChoice>,
+    wait: Res<WaitForLoop>,
+    limit: Res<GhostLimit>,
+    selected: Res<SelectedGhost>,
+    deliveries: Res<Deliveries>,
+    bindings: Res<Bindings>,
+) {
+    let Ok(mut text) = texts.get_single_mut() else {
+        return;
+    };
+
+    let (status, color) = match recordings.get_single().ok().map(|recording| (recording, recording.state(&clock))) {
+        Some((recording, RecordingState::Countdown)) => (
+            format!("STARTS IN {:.1}s, record again to cancel", recording.time_to_start(&clock).as_secs_f32()),
+            WAITING_COLOR,
+        ),
+        Some((recording, RecordingState::Recording)) => (
+            format!("REC {:.1}s left, record again to cancel", recording.time_left(&clock).as_secs_f32()),
+            RECORDING_COLOR,
+        ),
+        Some((_, RecordingState::Committing)) => ("REC done".to_string(), RECORDING_COLOR),
+        None => (
+            format!(
+                "{:?} loop {:.1}s{}",
+                *choice,
+                choice.length(loop_length.0).as_secs_f32(),
+                if wait.0 { ", starts with the loop" } else { "" },
+            ),
+            Color::WHITE,
+        ),
+    };
+
+    let ghost_count = ghosts.iter().count();
+    let ghosts = match limit.0 {
+        Some(max) => format!("Ghosts {}/{}", ghost_count, max),
+        None => format!("Ghosts {}", ghost_count),
+    };
+
+    let mut lines = vec![status, ghosts, format!("Delivered {}", deliveries.total)];
+
+    if selected.0.is_some() {
+        lines.push(format!(
+            "Ghost selected: {} delete, {} mute, {} to replace",
+            bindings.describe(HandActions::DeleteGhost),
+            bindings.describe(HandActions::MuteGhost),
+            bindings.describe(HandActions::Record),
+        ));
+    }
+
+    text.sections[0].value = lines.join("\n");
+    text.sections[0].style.color = color;
+}
+
+fn update_bar(
+    mut bars: Query<(&mut Style, &mut BackgroundColor), With<LoopBar>>,
+    recordings: Query<&Recording>,
+    clock: Res<LoopClock>,
+    loop_length: Res<LoopLength>,
+) {
+    let Ok((mut style, mut background)) = bars.get_single_mut() else {
+        return;
+    };
+
+    // a recording shows how much of it is done, otherwise the level's loop
+    let (progress, color) = match recordings.get_single().ok() {
+        Some(recording) if recording.state(&clock) == RecordingState::Countdown => (0.0, WAITING_COLOR),
+        Some(recording) => (recording.progress(&clock), RECORDING_COLOR),
+        None => (
+            clock.phase(loop_length.0).as_secs_f32() / loop_length.0.as_secs_f32().max(f32::EPSILON),
+            LOOP_COLOR,
+        ),
+    };
+
+    style.width = Val::Px(BAR_WIDTH * progress.clamp(0.0, 1.0));
+    *background = color.into();
+}
+
+fn draw_trails(
+    mut gizmos: Gizmos,
+    ghosts: Query<(Entity, &Playback)>,
+    selected: Res<SelectedGhost>,
+) {
+    for (ghost, playback) in ghosts.iter() {
+        let color = if selected.0 == Some(ghost) { SELECTED_TRAIL_COLOR } else { TRAIL_COLOR };
+
+        gizmos.linestrip_2d(playback.record().map(|(_, goal)| *goal), color);
+    }
+}
@@ -1,65 +1,398 @@
+use std::fmt;
+use std::time::Duration;
+
 use avian2d::prelude::*;
+use bevy::asset::{io::Reader, AssetLoadFailedEvent, AssetLoader, AsyncReadExt, LoadContext};
 use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
-use hand::{CurrentHand, HandActions, Playback};
+use hand::{CurrentHand, GhostLimit, Grabbing, HandActions, LoopClock, LoopLength, Playback, DEFAULT_LOOP_LENGTH};
 use leafwing_input_manager::prelude::ActionState;
-use object::{Collector, GrabInteractions, Grabbable, Object, ObjectInfo};
-use serde::Deserialize;
+use object::{Collector, GrabInteractions, Grabbable, Item, Object, ObjectInfo, Throwable, DEFAULT_MAX_THROW_SPEED};
+use serde::{Deserialize, Serialize};
+use state::{in_play, GameState};
+use submit::{Deliveries, LevelGoal, LevelTime, WinCondition};
 use bevy::reflect::TypePath;
 use bevy_common_assets::toml::TomlAssetPlugin;
 
 use super::*;
-//
-//TODO! make load next level to avoid annoying
+
+/// Loads the level with this id from the manifest.
 #[derive(Event)]
-pub struct LoadLevel(pub i16);
+pub struct LoadLevel(pub String);
+
+/// Loads the level after the current one, or finishes the campaign after the last one.
+#[derive(Event)]
+pub struct NextLevel;
+
+/// Sent when the level's goal has been reached.
+#[derive(Event)]
+pub struct LevelCompleted;
+
+/// Sent when asked for the level after the last one in the manifest.
+#[derive(Event)]
+pub struct CampaignComplete;
+
+/// Id of the level that is loaded, `None` until one has been picked.
+#[derive(Resource)]
+pub struct CurrentLevel(pub Option<String>);
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LevelEntry {
+    pub id: String,
+    pub name: String,
+    pub file: String,
+}
+
+/// Every level in the game, listed in the order they unlock.
+#[derive(Deserialize, Debug, Asset, TypePath, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LevelManifest {
+    pub levels: Vec<LevelEntry>,
+}
+
+impl LevelManifest {
+    pub fn get(&self, id: &str) -> Option<&LevelEntry> {
+        self.levels.iter().find(|level| level.id == id)
+    }
+
+    pub fn index_of(&self, id: &str) -> Option<usize> {
+        self.levels.iter().position(|level| level.id == id)
+    }
+
+    /// The level unlocked by finishing `id`, `None` if it is the last one.
+    pub fn next(&self, id: &str) -> Option<&LevelEntry> {
+        self.levels.get(self.index_of(id)? + 1)
+    }
+}
 
 #[derive(Resource)]
-pub struct CurrentLevel(pub i16);
+pub struct ManifestHandle(pub Handle<LevelManifest>);
 
-#[derive(Component, Deserialize, Debug, Clone)]
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct ColliderInfo {
     pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub size: Option<Vec<f32>>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
-struct LoadObject {
-    texture_name: Option<String>,
-    position: Vec<f32>,
-    scale: Vec<f32>,
-    collector: Option<Collector>,
-    body_static: Option<bool>,
-    grabbable: Option<bool>,
-    collider_info: Option<ColliderInfo>,
-    grab: Option<GrabInteractions>,
-    sensor: Option<bool>,
-    anchored: Option<bool>,
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct LoadObject {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub texture_name: Option<String>,
+    /// what the object is for collectors and goals, the sprite doesn't matter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item: Option<String>,
+    /// extra names collectors and goals can match the item by
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    pub position: Vec<f32>,
+    pub scale: Vec<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collector: Option<Collector>,
+    #[serde(rename = "static", skip_serializing_if = "Option::is_none")]
+    pub body_static: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grabbable: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collider_info: Option<ColliderInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grab: Option<GrabInteractions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sensor: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchored: Option<bool>,
+    /// keeps the hand's velocity when let go, true unless set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub throwable: Option<bool>,
+    /// in pixels per second, faster throws are slowed down to it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_throw_speed: Option<f32>,
+}
+
+/// The level file entry an object was spawned from.
+#[derive(Component)]
+pub struct LevelObject {
+    pub index: usize,
+    pub source: LoadObject,
 }
 
 #[derive(Resource)]
 struct LevelHandle(Option<Handle<Level>>);
 
-#[derive(Deserialize, Debug, Asset, TypePath, Clone)]
-struct Level {
-    objects: Vec<LoadObject>,
-    background_color: Vec<f32>,
+/// The level that is currently spawned.
+#[derive(Resource)]
+pub struct ActiveLevel(pub Handle<Level>);
+
+/// Rebuilds the level whenever its file changes, run with `--features hot_reload` to watch the files.
+#[derive(Resource)]
+pub struct HotReload {
+    /// keep ghost hands playing instead of clearing them with the rest of the level
+    pub keep_ghosts: bool,
+}
+
+impl Default for HotReload {
+    fn default() -> Self {
+        Self { keep_ghosts: true }
+    }
+}
+
+// set when the pending load is a hot reload of the active level
+#[derive(Resource, Default)]
+struct Reloading(bool);
+
+/// The level as the editor last saved it. Hot reload skips changes that only bring the level in
+/// line with it, so saving doesn't rebuild the level under the editor.
+#[derive(Resource, Default)]
+pub struct EditorSave(pub Option<String>);
+
+/// Longest `loop_length` a level can ask for, in seconds.
+const MAX_LOOP_LENGTH: f32 = 3600.0;
+
+#[derive(Serialize, Deserialize, Debug, Asset, TypePath, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Level {
+    pub background_color: Vec<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goal: Option<WinCondition>,
+    /// seconds, recordings loop this often
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loop_length: Option<f32>,
+    /// most ghost hands that can play at once, no limit if left out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_ghosts: Option<u32>,
+    pub objects: Vec<LoadObject>,
+}
+
+/// Why a level file could not be loaded, pointing at the offending object and field.
+#[derive(Debug, Clone)]
+pub struct LevelError {
+    pub file: String,
+    pub object: Option<usize>,
+    pub field: Option<String>,
+    pub reason: String,
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.file)?;
+
+        if let Some(object) = self.object {
+            write!(f, ": objects[{}]", object)?;
+        }
+
+        if let Some(field) = &self.field {
+            write!(f, ".{}", field)?;
+        }
+
+        write!(f, ": {}", self.reason)
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+impl LevelError {
+    fn new(file: &str, object: Option<usize>, field: Option<&str>, reason: impl Into<String>) -> Self {
+        Self {
+            file: file.into(),
+            object,
+            field: field.map(String::from),
+            reason: reason.into(),
+        }
+    }
+
+    // serde names the field in backticks, e.g. "unknown field `static`, expected one of ..."
+    fn from_toml(file: &str, object: Option<usize>, error: toml::de::Error) -> Self {
+        let reason = error.message().to_string();
+        let field = reason.split('`').nth(1).filter(|_| reason.contains("field `"));
+
+        Self::new(file, object, field, reason.clone())
+    }
+}
+
+#[derive(Default)]
+struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    type Asset = Level;
+    type Settings = ();
+    type Error = LevelError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Level, LevelError> {
+        let file = load_context.path().display().to_string();
+
+        let mut text = String::new();
+        reader.read_to_string(&mut text).await
+            .map_err(|e| LevelError::new(&file, None, None, e.to_string()))?;
+
+        let level = Level::parse(&file, &text)?;
+        level.validate(&file)?;
+
+        Ok(level)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.toml"]
+    }
+}
+
+impl Level {
+    pub fn parse(file: &str, text: &str) -> Result<Self, LevelError> {
+        let table: toml::Table = toml::from_str(text)
+            .map_err(|e| LevelError::from_toml(file, None, e))?;
+
+        // Deserialize objects one by one first so errors can name the object they came from
+        if let Some(toml::Value::Array(objects)) = table.get("objects") {
+            for (index, object) in objects.iter().enumerate() {
+                LoadObject::deserialize(object.clone())
+                    .map_err(|e| LevelError::from_toml(file, Some(index), e))?;
+            }
+        }
+
+        Level::deserialize(toml::Value::Table(table))
+            .map_err(|e| LevelError::from_toml(file, None, e))
+    }
+
+    pub fn validate(&self, file: &str) -> Result<(), LevelError> {
+        if self.background_color.len() != 3 {
+            return Err(LevelError::new(file, None, Some("background_color"), format!(
+                "expected 3 values (hue, saturation, lightness), found {}", self.background_color.len()
+            )));
+        }
+
+        if let Some(loop_length) = self.loop_length {
+            if !(loop_length > 0.0 && loop_length <= MAX_LOOP_LENGTH) {
+                return Err(LevelError::new(file, None, Some("loop_length"), format!(
+                    "expected a number of seconds above 0 and at most {}, found {}", MAX_LOOP_LENGTH, loop_length
+                )));
+            }
+        }
+
+        // every item kind and tag used in the level
+        let labels: Vec<&String> = self.objects.iter()
+            .flat_map(|object| object.item.iter().chain(object.tags.iter().flatten()))
+            .collect();
+
+        if let Some(goal) = &self.goal {
+            if let Some(reason) = goal.time_limit_error() {
+                return Err(LevelError::new(file, None, Some("goal"), reason));
+            }
+
+            for item in goal.items() {
+                if !labels.contains(&item) {
+                    return Err(LevelError::new(file, None, Some("goal"), format!(
+                        "no object in this level is an item or tag \"{}\"", item
+                    )));
+                }
+            }
+        }
+
+        for (index, object) in self.objects.iter().enumerate() {
+            if object.position.len() != 3 {
+                return Err(LevelError::new(file, Some(index), Some("position"), format!(
+                    "expected 3 values (x, y, z), found {}", object.position.len()
+                )));
+            }
+
+            if object.scale.len() != 2 {
+                return Err(LevelError::new(file, Some(index), Some("scale"), format!(
+                    "expected 2 values (x, y), found {}", object.scale.len()
+                )));
+            }
+
+            if object.tags.is_some() && object.item.is_none() {
+                return Err(LevelError::new(file, Some(index), Some("tags"), "tags need an item kind, add an item"));
+            }
+
+            if let Some(max_throw_speed) = object.max_throw_speed {
+                if !(max_throw_speed > 0.0 && max_throw_speed.is_finite()) {
+                    return Err(LevelError::new(file, Some(index), Some("max_throw_speed"), format!(
+                        "expected a positive number of pixels per second, found {}", max_throw_speed
+                    )));
+                }
+
+                if object.throwable == Some(false) {
+                    return Err(LevelError::new(file, Some(index), Some("max_throw_speed"), "the object isn't throwable, remove it or throwable = false"));
+                }
+            }
+
+            if let Some(collector) = &object.collector {
+                if let Some(tag) = collector.collecting.iter().find(|tag| !labels.contains(tag)) {
+                    return Err(LevelError::new(file, Some(index), Some("collector.collecting"), format!(
+                        "no object in this level is an item or tag \"{}\"", tag
+                    )));
+                }
+
+                if let Some(orientation) = collector.orientation.filter(|orientation| !orientation.is_finite()) {
+                    return Err(LevelError::new(file, Some(index), Some("collector.orientation"), format!(
+                        "expected degrees, found {}", orientation
+                    )));
+                }
+            }
+
+            if let Some(collider_info) = &object.collider_info {
+                match collider_info.name.as_str() {
+                    "segment" => {
+                        let size = collider_info.size.as_ref().map_or(0, |size| size.len());
+
+                        if size != 2 {
+                            return Err(LevelError::new(file, Some(index), Some("collider_info.size"), format!(
+                                "a segment needs 2 values (x, y), found {}", size
+                            )));
+                        }
+                    }
+                    "rectangle" | "circle" => {}
+                    name => {
+                        return Err(LevelError::new(file, Some(index), Some("collider_info.name"), format!(
+                            "unknown collider \"{}\", expected rectangle, circle or segment", name
+                        )));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
+#[derive(Component)]
+struct LevelErrorText;
+
+
 pub(super) fn register(app: &mut App) {
     app
-        .add_plugins(TomlAssetPlugin::<Level>::new(&["level.toml"]))
-        .insert_resource(CurrentLevel(0))
+        .init_asset::<Level>()
+        .init_asset_loader::<LevelLoader>()
+        .add_plugins(TomlAssetPlugin::<LevelManifest>::new(&["manifest.toml"]))
+        .insert_resource(CurrentLevel(None))
+        .insert_resource(LevelHandle(None))
+        .init_resource::<HotReload>()
+        .init_resource::<Reloading>()
+        .init_resource::<EditorSave>()
         .add_event::<LoadLevel>()
+        .add_event::<NextLevel>()
+        .add_event::<LevelCompleted>()
+        .add_event::<CampaignComplete>()
         .add_systems(Startup, setup)
-        .add_systems(Update, (load_level, reload_level, load_event));
+        .add_systems(Update, (
+            (reload_level.run_if(in_play), next_level, load_event, hot_reload, load_level).chain(),
+            show_level_errors,
+        ))
+        .add_systems(OnEnter(GameState::MainMenu), unload_level)
+        .add_systems(OnEnter(GameState::LevelSelect), unload_level);
 }
 
 fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
 ){
-    let level = LevelHandle(Some(asset_server.load("levels/0.level.toml")));
-    commands.insert_resource(level);
+    commands.insert_resource(ManifestHandle(asset_server.load("levels/campaign.manifest.toml")));
 }
 
 fn reload_level(
@@ -72,20 +405,171 @@ fn reload_level(
             continue;
         }
 
-        ev_level.send(LoadLevel(current_level.0));
+        if let Some(id) = &current_level.0 {
+            ev_level.send(LoadLevel(id.clone()));
+        }
+    }
+}
+
+fn next_level(
+    mut ev_next: EventReader<NextLevel>,
+    mut ev_level: EventWriter<LoadLevel>,
+    mut ev_complete: EventWriter<CampaignComplete>,
+    current_level: Res<CurrentLevel>,
+    manifest: Res<ManifestHandle>,
+    manifests: Res<Assets<LevelManifest>>,
+) {
+    // several collectors can finish the level in the same frame
+    if ev_next.read().count() == 0 {
+        return;
+    }
+
+    let (Some(manifest), Some(id)) = (manifests.get(&manifest.0), &current_level.0) else {
+        return;
+    };
+
+    match manifest.next(id) {
+        Some(next) => {
+            ev_level.send(LoadLevel(next.id.clone()));
+        }
+        None => {
+            ev_complete.send(CampaignComplete);
+        }
     }
 }
 
 fn load_event(
-    mut commands: Commands,
     mut ev_level: EventReader<LoadLevel>,
     asset_server: Res<AssetServer>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut the_level: ResMut<LevelHandle>,
+    manifest: Res<ManifestHandle>,
+    manifests: Res<Assets<LevelManifest>>,
 ) {
+    let Some(manifest) = manifests.get(&manifest.0) else {
+        return;
+    };
+
     for ev in ev_level.read() {
-        dbg!(ev.0);
-        commands.insert_resource(CurrentLevel(ev.0));
-        let level = LevelHandle( Some(asset_server.load(format!("levels/{}.level.toml", ev.0))) );
-        commands.insert_resource(level);
+        let Some(entry) = manifest.get(&ev.0) else {
+            error!("level \"{}\" is not listed in levels/campaign.manifest.toml", ev.0);
+            continue;
+        };
+
+        info!("loading level {} ({})", entry.id, entry.file);
+        current_level.0 = Some(entry.id.clone());
+        *the_level = LevelHandle( Some(asset_server.load(entry.file.clone())) );
+    }
+}
+
+fn unload_level(
+    mut commands: Commands,
+    objects: Query<Entity, With<Object>>,
+    hands: Query<Entity, With<Playback>>,
+    grabbing: Query<(Entity, &Grabbing)>,
+    error_text: Query<Entity, With<LevelErrorText>>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut the_level: ResMut<LevelHandle>,
+) {
+    despawn_objects(&mut commands, &objects, &grabbing);
+    despawn_ghosts(&mut commands, &hands);
+
+    for text in error_text.iter() {
+        commands.entity(text).despawn();
+    }
+
+    commands.remove_resource::<ActiveLevel>();
+    current_level.0 = None;
+    *the_level = LevelHandle(None);
+}
+
+fn hot_reload(
+    mut ev_asset: EventReader<AssetEvent<Level>>,
+    active_level: Option<Res<ActiveLevel>>,
+    levels: Res<Assets<Level>>,
+    editor_save: Res<EditorSave>,
+    mut the_level: ResMut<LevelHandle>,
+    mut reloading: ResMut<Reloading>,
+) {
+    let Some(active_level) = active_level else {
+        ev_asset.clear();
+        return;
+    };
+
+    let modified = ev_asset.read().any(|ev| ev.is_modified(&active_level.0));
+
+    // the editor's own save, first put in the assets by the editor and then by the file watcher
+    let saved_by_editor = editor_save.0.is_some() && levels.get(&active_level.0)
+        .and_then(|level| toml::to_string_pretty(level).ok())
+        == editor_save.0;
+
+    if saved_by_editor {
+        return;
+    }
+
+    // a level that was asked for explicitly wins over reloading the old one
+    if modified && the_level.0.is_none() {
+        info!("level file changed, reloading");
+        *the_level = LevelHandle(Some(active_level.0.clone()));
+        reloading.0 = true;
+    }
+}
+
+fn despawn_objects(
+    commands: &mut Commands,
+    objects: &Query<Entity, With<Object>>,
+    grabbing: &Query<(Entity, &Grabbing)>,
+) {
+    for object in objects.iter() {
+        commands.entity(object).despawn();
+    }
+
+    // let go of anything held, the joints would point at despawned objects
+    for (hand, grabbing) in grabbing.iter() {
+        commands.entity(grabbing.0).despawn();
+        commands.entity(hand).remove::<Grabbing>();
+    }
+}
+
+fn despawn_ghosts(
+    commands: &mut Commands,
+    hands: &Query<Entity, With<Playback>>,
+) {
+    for hand in hands.iter() {
+        commands.entity(hand).despawn_descendants();
+        commands.entity(hand).despawn();
+    }
+}
+
+fn show_level_errors(
+    mut commands: Commands,
+    mut ev_failed: EventReader<AssetLoadFailedEvent<Level>>,
+    error_text: Query<Entity, With<LevelErrorText>>,
+) {
+    for ev in ev_failed.read() {
+        error!("{}", ev.error);
+
+        for entity in error_text.iter() {
+            commands.entity(entity).despawn();
+        }
+
+        commands.spawn((
+            TextBundle::from_section(
+                format!("Level failed to load\n{}", ev.error),
+                TextStyle {
+                    font_size: 20.0,
+                    color: Color::srgb(0.8, 0.1, 0.1),
+                    ..default()
+                },
+            ).with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                left: Val::Px(10.0),
+                max_width: Val::Px(SCREEN_W - 20.0),
+                ..default()
+            }),
+            LevelErrorText,
+        ));
     }
 }
 
@@ -95,10 +579,14 @@ fn load_level(
     asset_server: Res<AssetServer>,
     objects: Query<Entity, With<Object>>,
     hands: Query<Entity, With<Playback>>,
+    grabbing: Query<(Entity, &Grabbing)>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut levels: ResMut<Assets<Level>>,
     mut the_level: ResMut<LevelHandle>,
+    error_text: Query<Entity, With<LevelErrorText>>,
+    hot_reload: Res<HotReload>,
+    mut reloading: ResMut<Reloading>,
 ) {
     if the_level.0.is_none() {
         return;
@@ -108,17 +596,26 @@ fn load_level(
 
         let level = level.clone();
 
+        commands.insert_resource(ActiveLevel(the_level.0.clone().unwrap()));
         *the_level = LevelHandle(None);
 
-        for object in objects.iter() {
-            commands.entity(object).despawn();
+        despawn_objects(&mut commands, &objects, &grabbing);
+
+        if !(std::mem::take(&mut reloading.0) && hot_reload.keep_ghosts) {
+            despawn_ghosts(&mut commands, &hands);
         }
 
-        for hand in hands.iter() {
-            commands.entity(hand).despawn_descendants();
-            commands.entity(hand).despawn();
+        for text in error_text.iter() {
+            commands.entity(text).despawn();
         }
 
+        commands.insert_resource(LevelGoal(level.goal.clone()));
+        commands.insert_resource(Deliveries::default());
+        commands.insert_resource(LevelTime::default());
+        commands.insert_resource(LoopClock::default());
+        commands.insert_resource(GhostLimit(level.max_ghosts));
+        commands.insert_resource(LoopLength(Duration::from_secs_f32(level.loop_length.unwrap_or(DEFAULT_LOOP_LENGTH))));
+
         let background_color = level.background_color;
 
         //background
@@ -132,78 +629,326 @@ fn load_level(
             Object
         ));
 
-        for object in level.objects {
-            let mut e = commands.spawn(Object);
+        for (index, object) in level.objects.into_iter().enumerate() {
+            spawn_object(&mut commands, &asset_server, index, object);
+        }
+    }
+}
 
-            if object.texture_name.is_some() {
-                let texture = asset_server.load(object.texture_name.clone().unwrap());
-                let mut grab = GrabInteractions::Grab;
+/// Spawns one object of a level file, `index` is its position in the file's `objects`.
+pub fn spawn_object(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    index: usize,
+    object: LoadObject,
+) -> Entity {
+    let mut e = commands.spawn((
+        Object,
+        LevelObject {
+            index,
+            source: object.clone(),
+        },
+    ));
 
-                if object.grab.is_some() {
-                    grab = object.grab.unwrap();
-                }
+    if object.texture_name.is_some() {
+        let texture = asset_server.load(object.texture_name.clone().unwrap());
+        let mut grab = GrabInteractions::Grab;
 
-                e.insert((
-                    SpriteBundle {
-                        texture,
-                        transform: Transform::from_xyz(object.position[0], object.position[1], object.position[2])
-                            .with_scale(Vec3::new(object.scale[0], object.scale[1], 1.0)),
-                        ..default()
-                    },
-                    ObjectInfo {
-                        grab,
-                        texture_name: object.texture_name.clone().unwrap(),
-                    },
-                ));
-            } else {
-                e.insert(TransformBundle::from_transform(Transform::from_xyz(object.position[0], object.position[1], object.position[2])));
-            }
+        if object.grab.is_some() {
+            grab = object.grab.unwrap();
+        }
 
-            //TODO! wow all of this code sucks ass
-            // I just learned how cool the toml crate is and how better you can make this lol!
-            if object.collector.is_some() {
-                e.insert(object.collector.unwrap());
-            }
+        e.insert((
+            SpriteBundle {
+                texture,
+                transform: Transform::from_xyz(object.position[0], object.position[1], object.position[2])
+                    .with_scale(Vec3::new(object.scale[0], object.scale[1], 1.0)),
+                ..default()
+            },
+            ObjectInfo {
+                grab,
+                texture_name: object.texture_name.clone().unwrap(),
+            },
+        ));
+    } else {
+        e.insert(TransformBundle::from_transform(Transform::from_xyz(object.position[0], object.position[1], object.position[2])));
+    }
 
-            if object.anchored.is_some() {
-                e.insert(LockedAxes::ALL_LOCKED);
-            }
+    if let Some(kind) = object.item.clone() {
+        e.insert(Item {
+            kind,
+            tags: object.tags.clone().unwrap_or_default(),
+        });
+    }
 
-            if object.sensor.is_some() {
-                e.insert(Sensor);
-            }
+    //TODO! wow all of this code sucks ass
+    // I just learned how cool the toml crate is and how better you can make this lol!
+    if object.collector.is_some() {
+        e.insert(object.collector.unwrap());
+    }
 
-            if object.body_static.is_some() {
-                e.insert(RigidBody::Static);
-            } else {
-                e.insert((
-                    RigidBody::Dynamic,
-                    LinearDamping(1.0),
-                ));
-            }
+    if object.anchored == Some(true) {
+        e.insert(LockedAxes::ALL_LOCKED);
+    }
 
-            if object.grabbable.is_some() {
-                e.insert(Grabbable);
-            }
+    if object.sensor == Some(true) {
+        e.insert(Sensor);
+    }
 
-            if object.collider_info.is_some() {
-                let collider_info = object.collider_info.unwrap();
+    if object.body_static == Some(true) {
+        e.insert(RigidBody::Static);
+    } else {
+        e.insert((
+            RigidBody::Dynamic,
+            LinearDamping(1.0),
+        ));
+    }
 
-                match collider_info.clone().name.as_str() {
-                    "segment" => {
-                        let size = collider_info.size.unwrap().clone();
-                        e.insert(Collider::segment(Vec2::new(0.0, 0.0), Vec2::new(size[0], size[1])));
-                    }
-                    _ => {
-                        e.insert(collider_info);
-                    }
-                }
-            } else {
-                e.insert(ColliderInfo {
-                    name: "rectangle".into(),
-                    size: None,
-                });
+    if object.grabbable == Some(true) {
+        e.insert(Grabbable);
+    }
+
+    if object.throwable != Some(false) {
+        e.insert(Throwable {
+            max_speed: object.max_throw_speed.unwrap_or(DEFAULT_MAX_THROW_SPEED),
+        });
+    }
+
+    if object.collider_info.is_some() {
+        let collider_info = object.collider_info.unwrap();
+
+        match collider_info.clone().name.as_str() {
+            "segment" => {
+                let size = collider_info.size.unwrap().clone();
+                e.insert(Collider::segment(Vec2::new(0.0, 0.0), Vec2::new(size[0], size[1])));
+            }
+            _ => {
+                e.insert(collider_info);
             }
         }
+    } else {
+        e.insert(ColliderInfo {
+            name: "rectangle".into(),
+            size: None,
+        });
+    }
+
+    e.id()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // what the loader does with a file's text
+    fn load(text: &str) -> Result<Level, LevelError> {
+        let level = Level::parse("test.level.toml", text)?;
+        level.validate("test.level.toml")?;
+
+        Ok(level)
+    }
+
+    #[test]
+    fn loads_a_valid_level() {
+        let level = load(r#"
+            background_color = [30, 0.25, 0.91]
+
+            [[objects]]
+            texture_name = "card.png"
+            item = "card"
+            tags = ["paper"]
+            position = [0, 0, 0]
+            scale = [1, 1]
+            collider_info = { name = "circle" }
+
+            [[objects]]
+            position = [100, 0, -1]
+            scale = [1, 1]
+            collector = { collecting = ["paper", "card"], interaction = "Count" }
+
+            [[objects]]
+            position = [-640, -360, 0]
+            scale = [1, 1]
+            static = true
+            collider_info = { name = "segment", size = [0, 720] }
+        "#).unwrap();
+
+        assert_eq!(level.objects.len(), 3);
+    }
+
+    #[test]
+    fn unknown_field_names_the_object() {
+        let error = load(r#"
+            background_color = [30, 0.25, 0.91]
+
+            [[objects]]
+            position = [0, 0, 0]
+            scale = [1, 1]
+
+            [[objects]]
+            position = [0, 0, 0]
+            scale = [1, 1]
+            grabable = true
+        "#).unwrap_err();
+
+        assert_eq!(error.object, Some(1));
+        assert_eq!(error.field.as_deref(), Some("grabable"));
+    }
+
+    #[test]
+    fn wrong_number_of_values() {
+        let background = load("background_color = [30, 0.25]\nobjects = []").unwrap_err();
+        assert_eq!((background.object, background.field.as_deref()), (None, Some("background_color")));
+
+        let position = load(r#"
+            background_color = [30, 0.25, 0.91]
+
+            [[objects]]
+            position = [0, 0]
+            scale = [1, 1]
+        "#).unwrap_err();
+        assert_eq!((position.object, position.field.as_deref()), (Some(0), Some("position")));
+    }
+
+    #[test]
+    fn collector_needs_an_item_in_the_level() {
+        let error = load(r#"
+            background_color = [30, 0.25, 0.91]
+
+            [[objects]]
+            texture_name = "card.png"
+            position = [0, 0, 0]
+            scale = [1, 1]
+
+            [[objects]]
+            position = [100, 0, -1]
+            scale = [1, 1]
+            collector = { collecting = "card.png", interaction = "Count" }
+        "#).unwrap_err();
+
+        assert_eq!(error.object, Some(1));
+        assert_eq!(error.field.as_deref(), Some("collector.collecting"));
+    }
+
+    #[test]
+    fn tags_need_an_item() {
+        let error = load(r#"
+            background_color = [30, 0.25, 0.91]
+
+            [[objects]]
+            tags = ["paper"]
+            position = [0, 0, 0]
+            scale = [1, 1]
+        "#).unwrap_err();
+
+        assert_eq!(error.field.as_deref(), Some("tags"));
+    }
+
+    #[test]
+    fn loop_length_is_bounded() {
+        for loop_length in ["0", "-3", "nan", "inf", "1e30"] {
+            let error = load(&format!("background_color = [30, 0.25, 0.91]\nloop_length = {}\nobjects = []", loop_length)).unwrap_err();
+
+            assert_eq!(error.field.as_deref(), Some("loop_length"), "accepted loop_length = {}", loop_length);
+        }
+
+        assert!(load("background_color = [30, 0.25, 0.91]\nloop_length = 6.5\nobjects = []").is_ok());
+    }
+
+    #[test]
+    fn max_throw_speed_is_checked() {
+        let object = |fields: &str| format!(r#"
+            background_color = [30, 0.25, 0.91]
+
+            [[objects]]
+            position = [0, 0, 0]
+            scale = [1, 1]
+            {}
+        "#, fields);
+
+        assert!(load(&object("max_throw_speed = 800")).is_ok());
+
+        for fields in ["max_throw_speed = 0", "max_throw_speed = nan", "max_throw_speed = 800\nthrowable = false"] {
+            let error = load(&object(fields)).unwrap_err();
+
+            assert_eq!(error.field.as_deref(), Some("max_throw_speed"), "accepted {}", fields);
+        }
+    }
+
+    #[test]
+    fn collector_orientation_is_checked() {
+        let collector = |orientation: &str| load(&format!(r#"
+            background_color = [30, 0.25, 0.91]
+
+            [[objects]]
+            item = "card"
+            position = [0, 0, 0]
+            scale = [1, 1]
+
+            [[objects]]
+            position = [100, 0, -1]
+            scale = [1, 1]
+            collector = {{ collecting = "card", interaction = "Count", orientation = {} }}
+        "#, orientation));
+
+        assert!(collector("90").is_ok());
+        assert_eq!(collector("nan").unwrap_err().field.as_deref(), Some("collector.orientation"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn goal_is_checked() {
+        let missing = load(r#"
+            background_color = [30, 0.25, 0.91]
+            goal = { deliver = { item = "coin", count = 1 } }
+
+            [[objects]]
+            item = "card"
+            position = [0, 0, 0]
+            scale = [1, 1]
+        "#).unwrap_err();
+        assert_eq!(missing.field.as_deref(), Some("goal"));
+
+        let unlimited = load(r#"
+            background_color = [30, 0.25, 0.91]
+            goal = { any = [{ time_limit = { seconds = 10.0 } }, "hands_off"] }
+            objects = []
+        "#).unwrap_err();
+        assert_eq!(unlimited.field.as_deref(), Some("goal"));
+
+        assert!(load(r#"
+            background_color = [30, 0.25, 0.91]
+            goal = { all = [{ time_limit = { seconds = 10.0 } }, "hands_off"] }
+            objects = []
+        "#).is_ok());
+    }
+
+    #[test]
+    fn colliders_are_checked() {
+        let segment = load(r#"
+            background_color = [30, 0.25, 0.91]
+
+            [[objects]]
+            position = [0, 0, 0]
+            scale = [1, 1]
+            collider_info = { name = "segment", size = [720] }
+        "#).unwrap_err();
+        assert_eq!(segment.field.as_deref(), Some("collider_info.size"));
+
+        let unknown = load(r#"
+            background_color = [30, 0.25, 0.91]
+
+            [[objects]]
+            position = [0, 0, 0]
+            scale = [1, 1]
+            collider_info = { name = "triangle" }
+        "#).unwrap_err();
+        assert_eq!(unknown.field.as_deref(), Some("collider_info.name"));
+    }
+
+    #[test]
+    fn error_message_points_at_the_field() {
+        let error = LevelError::new("levels/0.level.toml", Some(2), Some("scale"), "expected 2 values (x, y), found 3");
+
+        assert_eq!(error.to_string(), "levels/0.level.toml: objects[2].scale: expected 2 values (x, y), found 3");
+    }
+}
@@ -0,0 +1,36 @@
+// Bevy code commonly triggers these lints and they may be important signals
+// about code quality. They are sometimes hard to avoid though, and the CI
+// workflow treats them as errors, so this allows them throughout the project.
+// Feel free to delete this line.
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
+use bevy::prelude::*;
+
+pub mod hand;
+pub mod object;
+mod physics;
+mod camera;
+pub mod level;
+pub mod submit;
+mod editor;
+pub mod state;
+mod menu;
+mod hud;
+pub mod save;
+
+pub const SCREEN_W : f32 = 1280.0;
+pub const SCREEN_H : f32 = 720.0;
+
+/// Adds every game system, used by the game itself and the headless `verify` binary.
+pub fn register(app: &mut App) {
+    hand::register(app);
+    object::register(app);
+    physics::register(app);
+    camera::register(app);
+    level::register(app);
+    submit::register(app);
+    editor::register(app);
+    state::register(app);
+    menu::register(app);
+    hud::register(app);
+}
@@ -1,22 +1,7 @@
-// Bevy code commonly triggers these lints and they may be important signals
-// about code quality. They are sometimes hard to avoid though, and the CI
-// workflow treats them as errors, so this allows them throughout the project.
-// Feel free to delete this line.
-#![allow(clippy::too_many_arguments, clippy::type_complexity)]
-
 use bevy::prelude::*;
 use bevy::asset::AssetMetaCheck;
 use bevy::window::WindowResolution;
-
-mod hand;
-mod object;
-mod physics;
-mod camera;
-mod level;
-mod submit;
-
-pub const SCREEN_W : f32 = 1280.0;
-pub const SCREEN_H : f32 = 720.0;
+use manos::{SCREEN_H, SCREEN_W};
 
 fn main() {
     let mut app = App::new();
@@ -35,12 +20,8 @@ fn main() {
             ..default()
         }));
 
-    hand::register(&mut app);
-    object::register(&mut app);
-    physics::register(&mut app);
-    camera::register(&mut app);
-    level::register(&mut app);
-    submit::register(&mut app);
+    manos::register(&mut app);
+    manos::save::register(&mut app);
 
     app.run();
-}
\ No newline at end of file
+}
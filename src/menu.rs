@@ -0,0 +1,370 @@
+use bevy::ecs::system::EntityCommands;
+use hand::{Binding, Bindings, HandActions};
+use level::{CurrentLevel, LevelManifest, LoadLevel, ManifestHandle, NextLevel};
+use state::{GameState, Progress};
+
+use super::*;
+
+#[derive(Component)]
+enum MenuButton {
+    Play,
+    LevelSelect,
+    Controls,
+    Rebind(HandActions),
+    ResetBindings,
+    Level(String),
+    Resume,
+    Retry,
+    Next,
+    MainMenu,
+    #[cfg(not(target_arch = "wasm32"))]
+    Quit,
+}
+
+const BUTTON_COLOR: Color = Color::srgb(0.2, 0.2, 0.25);
+const HOVER_COLOR: Color = Color::srgb(0.3, 0.3, 0.4);
+const LOCKED_COLOR: Color = Color::srgb(0.12, 0.12, 0.12);
+const CONFLICT_COLOR: Color = Color::srgb(1.0, 0.4, 0.4);
+
+/// The action waiting for a button to be pressed on the controls screen.
+#[derive(Resource, Default)]
+struct Rebinding {
+    action: Option<HandActions>,
+    // set once the click that picked the action has been let go
+    armed: bool,
+}
+
+#[derive(Component)]
+struct BindingLabel(HandActions);
+
+pub(super) fn register(app: &mut App) {
+    app
+        .add_systems(OnEnter(GameState::MainMenu), main_menu)
+        .add_systems(OnEnter(GameState::LevelSelect), level_select)
+        .add_systems(OnEnter(GameState::Controls), controls)
+        .add_systems(OnExit(GameState::Controls), stop_rebinding)
+        .add_systems(OnEnter(GameState::Paused), pause_menu)
+        .add_systems(OnEnter(GameState::LevelComplete), complete_menu)
+        .init_resource::<Rebinding>()
+        .add_systems(Update, (
+            button_colors,
+            (press_buttons, capture_binding).chain(),
+            binding_labels.run_if(in_state(GameState::Controls)),
+        ));
+}
+
+// a full screen column that is despawned when leaving `state`
+fn screen<'a>(commands: &'a mut Commands, state: GameState, background: Color) -> EntityCommands<'a> {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            background_color: background.into(),
+            ..default()
+        },
+        StateScoped(state),
+    ))
+}
+
+fn title(parent: &mut ChildBuilder, text: &str) {
+    parent.spawn(TextBundle::from_section(text, TextStyle {
+        font_size: 60.0,
+        color: Color::WHITE,
+        ..default()
+    }).with_style(Style {
+        margin: UiRect::bottom(Val::Px(30.0)),
+        ..default()
+    }));
+}
+
+// a button without an action is drawn greyed out and does nothing
+fn button(parent: &mut ChildBuilder, label: &str, action: Option<MenuButton>) {
+    let mut button = parent.spawn(ButtonBundle {
+        style: Style {
+            width: Val::Px(480.0),
+            height: Val::Px(50.0),
+            margin: UiRect::all(Val::Px(6.0)),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        background_color: if action.is_some() { BUTTON_COLOR } else { LOCKED_COLOR }.into(),
+        ..default()
+    });
+
+    button.with_children(|parent| {
+        parent.spawn(TextBundle::from_section(label, TextStyle {
+            font_size: 28.0,
+            color: Color::WHITE,
+            ..default()
+        }));
+    });
+
+    if let Some(action) = action {
+        button.insert(action);
+    }
+}
+
+fn main_menu(mut commands: Commands) {
+    screen(&mut commands, GameState::MainMenu, Color::srgb(0.1, 0.1, 0.12)).with_children(|parent| {
+        title(parent, "Manos");
+        button(parent, "Play", Some(MenuButton::Play));
+        button(parent, "Level select", Some(MenuButton::LevelSelect));
+        button(parent, "Controls", Some(MenuButton::Controls));
+        #[cfg(not(target_arch = "wasm32"))]
+        button(parent, "Quit", Some(MenuButton::Quit));
+    });
+}
+
+fn controls(mut commands: Commands, bindings: Res<Bindings>) {
+    screen(&mut commands, GameState::Controls, Color::srgb(0.1, 0.1, 0.12)).with_children(|parent| {
+        title(parent, "Controls");
+
+        // rows are labelled by `binding_labels`
+        for entry in bindings.actions.iter() {
+            parent.spawn((
+                ButtonBundle {
+                    style: Style {
+                        width: Val::Px(720.0),
+                        height: Val::Px(36.0),
+                        margin: UiRect::all(Val::Px(2.0)),
+                        padding: UiRect::horizontal(Val::Px(12.0)),
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: BUTTON_COLOR.into(),
+                    ..default()
+                },
+                MenuButton::Rebind(entry.action),
+            )).with_children(|parent| {
+                parent.spawn((
+                    TextBundle::from_section("", TextStyle {
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                        ..default()
+                    }),
+                    BindingLabel(entry.action),
+                ));
+            });
+        }
+
+        button(parent, "Reset to defaults", Some(MenuButton::ResetBindings));
+        button(parent, "Back", Some(MenuButton::MainMenu));
+    });
+}
+
+fn binding_labels(
+    mut labels: Query<(&mut Text, &BindingLabel)>,
+    bindings: Res<Bindings>,
+    new_labels: Query<(), Added<BindingLabel>>,
+    rebinding: Res<Rebinding>,
+) {
+    if !bindings.is_changed() && !rebinding.is_changed() && new_labels.is_empty() {
+        return;
+    }
+
+    for (mut text, label) in labels.iter_mut() {
+        let action = label.0;
+        let conflicts = bindings.conflicts(action);
+
+        let mut value = if rebinding.action == Some(action) {
+            format!("{:?}: press a key or button, Esc to cancel", action)
+        } else {
+            format!("{:?}: {}", action, bindings.describe(action))
+        };
+
+        if let Some((input, other)) = conflicts.first() {
+            value.push_str(&format!("  ({} is also {:?})", input, other));
+        }
+
+        text.sections[0].value = value;
+        text.sections[0].style.color = if conflicts.is_empty() { Color::WHITE } else { CONFLICT_COLOR };
+    }
+}
+
+fn capture_binding(
+    mut rebinding: ResMut<Rebinding>,
+    mut bindings: ResMut<Bindings>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+) {
+    let Some(action) = rebinding.action else {
+        return;
+    };
+
+    if !rebinding.armed {
+        rebinding.armed = mouse.get_pressed().next().is_none();
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Escape) {
+        *rebinding = Rebinding::default();
+        return;
+    }
+
+    let input = keys.get_just_pressed().next().map(|key| Binding::Key(*key))
+        .or_else(|| mouse.get_just_pressed().next().map(|button| Binding::Mouse(*button)))
+        .or_else(|| gamepad_buttons.get_just_pressed().next().map(|button| Binding::Gamepad(button.button_type)));
+
+    if let Some(input) = input {
+        bindings.rebind(action, input);
+        *rebinding = Rebinding::default();
+    }
+}
+
+fn stop_rebinding(mut rebinding: ResMut<Rebinding>) {
+    *rebinding = Rebinding::default();
+}
+
+fn level_select(
+    mut commands: Commands,
+    manifest: Res<ManifestHandle>,
+    manifests: Res<Assets<LevelManifest>>,
+    progress: Res<Progress>,
+) {
+    let Some(manifest) = manifests.get(&manifest.0) else {
+        return;
+    };
+
+    screen(&mut commands, GameState::LevelSelect, Color::srgb(0.1, 0.1, 0.12)).with_children(|parent| {
+        title(parent, "Levels");
+
+        for (index, level) in manifest.levels.iter().enumerate() {
+            let unlocked = progress.is_unlocked(manifest, &level.id);
+            let mut label = format!("{}. {}", index + 1, level.name);
+
+            if let Some(best) = progress.best.get(&level.id) {
+                label.push_str(&format!(" ({:.1}s, {} ghosts)", best.time, best.ghosts));
+            } else if progress.completed.contains(&level.id) {
+                label.push_str(" (done)");
+            } else if !unlocked {
+                label.push_str(" (locked)");
+            }
+
+            button(parent, &label, unlocked.then(|| MenuButton::Level(level.id.clone())));
+        }
+
+        button(parent, "Back", Some(MenuButton::MainMenu));
+    });
+}
+
+fn pause_menu(mut commands: Commands) {
+    screen(&mut commands, GameState::Paused, Color::srgba(0.0, 0.0, 0.0, 0.6)).with_children(|parent| {
+        title(parent, "Paused");
+        button(parent, "Resume", Some(MenuButton::Resume));
+        button(parent, "Restart level", Some(MenuButton::Retry));
+        button(parent, "Level select", Some(MenuButton::LevelSelect));
+        button(parent, "Main menu", Some(MenuButton::MainMenu));
+    });
+}
+
+fn complete_menu(
+    mut commands: Commands,
+    current_level: Res<CurrentLevel>,
+    manifest: Res<ManifestHandle>,
+    manifests: Res<Assets<LevelManifest>>,
+) {
+    let next = manifests.get(&manifest.0)
+        .zip(current_level.0.as_ref())
+        .and_then(|(manifest, id)| manifest.next(id));
+
+    screen(&mut commands, GameState::LevelComplete, Color::srgba(0.0, 0.0, 0.0, 0.6)).with_children(|parent| {
+        match next {
+            Some(next) => {
+                title(parent, "Level complete!");
+                button(parent, &format!("Next: {}", next.name), Some(MenuButton::Next));
+            }
+            None => {
+                title(parent, "Every level is done, thanks for playing!");
+            }
+        }
+
+        button(parent, "Play again", Some(MenuButton::Retry));
+        button(parent, "Level select", Some(MenuButton::LevelSelect));
+        button(parent, "Main menu", Some(MenuButton::MainMenu));
+    });
+}
+
+fn button_colors(
+    mut buttons: Query<(&Interaction, &mut BackgroundColor), (Changed<Interaction>, With<MenuButton>)>,
+) {
+    for (interaction, mut color) in buttons.iter_mut() {
+        *color = match interaction {
+            Interaction::Hovered | Interaction::Pressed => HOVER_COLOR,
+            Interaction::None => BUTTON_COLOR,
+        }.into();
+    }
+}
+
+fn press_buttons(
+    buttons: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut ev_level: EventWriter<LoadLevel>,
+    mut ev_next: EventWriter<NextLevel>,
+    #[cfg(not(target_arch = "wasm32"))]
+    mut ev_exit: EventWriter<AppExit>,
+    current_level: Res<CurrentLevel>,
+    progress: Res<Progress>,
+    manifest: Res<ManifestHandle>,
+    manifests: Res<Assets<LevelManifest>>,
+    mut bindings: ResMut<Bindings>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    // the click may be the button being bound
+    if rebinding.action.is_some() {
+        return;
+    }
+
+    for (interaction, button) in buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match button {
+            MenuButton::Play => {
+                let Some(id) = manifests.get(&manifest.0).and_then(|manifest| progress.next_unfinished(manifest)) else {
+                    continue;
+                };
+
+                ev_level.send(LoadLevel(id.to_string()));
+                next_state.set(GameState::Playing);
+            }
+            MenuButton::LevelSelect => next_state.set(GameState::LevelSelect),
+            MenuButton::Controls => next_state.set(GameState::Controls),
+            MenuButton::Rebind(action) => {
+                *rebinding = Rebinding {
+                    action: Some(*action),
+                    armed: false,
+                };
+            }
+            MenuButton::ResetBindings => *bindings = Bindings::default(),
+            MenuButton::Level(id) => {
+                ev_level.send(LoadLevel(id.clone()));
+                next_state.set(GameState::Playing);
+            }
+            MenuButton::Resume => next_state.set(GameState::Playing),
+            MenuButton::Retry => {
+                if let Some(id) = &current_level.0 {
+                    ev_level.send(LoadLevel(id.clone()));
+                }
+                next_state.set(GameState::Playing);
+            }
+            MenuButton::Next => {
+                ev_next.send(NextLevel);
+                next_state.set(GameState::Playing);
+            }
+            MenuButton::MainMenu => next_state.set(GameState::MainMenu),
+            #[cfg(not(target_arch = "wasm32"))]
+            MenuButton::Quit => {
+                ev_exit.send(AppExit::Success);
+            }
+        }
+    }
+}
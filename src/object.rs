@@ -2,23 +2,89 @@ use super::*;
 
 use avian2d::prelude::*;
 use hand::Grabbing;
-use level::{ColliderInfo, CurrentLevel, LoadLevel};
-use serde::Deserialize;
-use submit::Deliveries;
+use level::{ColliderInfo, LevelCompleted};
+use serde::{Deserialize, Serialize};
+use state::in_play;
+use submit::{Deliveries, LevelTime};
 
-#[derive(Deserialize, Debug, Clone)]
-enum CollectorInteraction {
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum CollectorInteraction {
     FinishLevel,
     Count,
 }
 
-#[derive(Component, Deserialize, Debug, Clone)]
+/// One tag or a list of them, `collecting = "card"` or `collecting = ["card", "coin"]`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Tags {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Tags {
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        match self {
+            Tags::One(tag) => std::slice::from_ref(tag).iter(),
+            Tags::Many(tags) => tags.iter(),
+        }
+    }
+}
+
+/// How far in degrees an item can be turned from a collector's `orientation` and still fit.
+const ORIENTATION_TOLERANCE: f32 = 10.0;
+
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Collector {
-    collecting: String,
-    interaction: CollectorInteraction,
+    /// takes items whose kind or tags include any of these
+    pub collecting: Tags,
+    pub interaction: CollectorInteraction,
+    /// degrees counterclockwise the item has to be turned to, any way round if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<f32>,
+}
+
+impl Collector {
+    pub fn accepts(&self, item: &Item) -> bool {
+        self.collecting.iter().any(|tag| item.labels().any(|label| label == tag))
+    }
+
+    /// Whether an item turned `angle` radians counterclockwise fits.
+    pub fn fits(&self, angle: f32) -> bool {
+        let Some(orientation) = self.orientation else {
+            return true;
+        };
+
+        let difference = (angle.to_degrees() - orientation).rem_euclid(360.0);
+
+        difference.min(360.0 - difference) <= ORIENTATION_TOLERANCE
+    }
+}
+
+/// What an object is, independent of the sprite it's drawn with.
+#[derive(Component, Debug, Clone)]
+pub struct Item {
+    pub kind: String,
+    pub tags: Vec<String>,
+}
+
+impl Item {
+    /// The kind followed by the tags, what collectors and goals match against.
+    pub fn labels(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.kind).chain(self.tags.iter())
+    }
+}
+
+/// Fastest an object can be thrown unless its level sets `max_throw_speed`, in pixels per second.
+pub const DEFAULT_MAX_THROW_SPEED: f32 = 1500.0;
+
+/// Leaves with the hand's velocity when let go, objects without it just drop.
+#[derive(Component, Debug, Clone)]
+pub struct Throwable {
+    pub max_speed: f32,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum GrabInteractions {
     Grab,
     Spawn,
@@ -27,7 +93,7 @@ pub enum GrabInteractions {
 #[derive(Component)]
 pub struct ObjectInfo {
     pub grab: GrabInteractions,
-    // TODO! this is stupid but I need this to spawn new things with same image
+    // used to spawn copies with the same sprite
     pub texture_name: String,
 }
 
@@ -45,7 +111,7 @@ pub struct Grabbed(pub Entity);
 
 pub(super) fn register(app: &mut App) {
     app
-        .add_systems(Update, (add_image_size, collector_collide));
+        .add_systems(Update, (add_image_size, collector_collide.run_if(in_play)));
 }
 
 //TODO! Only sets image size once
@@ -88,41 +154,40 @@ fn add_image_size(
 fn collector_collide(
     mut commands: Commands,
     query: Query<(&CollidingEntities, &Collector)>,
-    objects: Query<&ObjectInfo>,
+    items: Query<(&Item, &Transform)>,
     is_grabbed: Query<&mut Grabbed>,
-    mut ev_level: EventWriter<LoadLevel>,
-    current_level: Res<CurrentLevel>,
+    grabbing: Query<&Grabbing>,
+    mut ev_level: EventWriter<LevelCompleted>,
     mut deliveries: ResMut<Deliveries>,
+    level_time: Res<LevelTime>,
 ) {
     for (colliding_entities, collector) in &query {
         for other_entity in colliding_entities.0.clone() {
-            let object = objects.get(other_entity);
-
-            if object.is_err() {
+            let Ok((item, transform)) = items.get(other_entity) else {
                 continue;
-            }
+            };
 
-            let object = object.unwrap();
+            let (angle, _, _) = transform.rotation.to_euler(EulerRot::ZYX);
 
-            if object.texture_name != collector.collecting {
+            if !collector.accepts(item) || !collector.fits(angle) {
                 continue;
             }
 
-            let grabbed = is_grabbed.get(other_entity);
-            if grabbed.is_ok() {
-                let hand = grabbed.unwrap().0;
-                
-                let mut hand_commands = commands.entity(hand);
-    
-                hand_commands.remove::<Grabbing>();
+            // the hand lets go, its joint would point at the despawned item
+            if let Ok(Grabbed(hand)) = is_grabbed.get(other_entity) {
+                if let Ok(Grabbing(joint)) = grabbing.get(*hand) {
+                    commands.entity(*joint).despawn();
+                }
+
+                commands.entity(*hand).remove::<Grabbing>();
             }
 
             match collector.interaction {
                 CollectorInteraction::FinishLevel => {
-                    ev_level.send(LoadLevel(current_level.0+1));
+                    ev_level.send(LevelCompleted);
                 }
                 CollectorInteraction::Count => {
-                    deliveries.0 += 1;
+                    deliveries.add(item, level_time.0.elapsed());
                 }
             }
 
@@ -131,4 +196,53 @@ fn collector_collide(
             e.despawn();
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collector(collecting: Tags, orientation: Option<f32>) -> Collector {
+        Collector { collecting, interaction: CollectorInteraction::Count, orientation }
+    }
+
+    fn item(kind: &str, tags: &[&str]) -> Item {
+        Item { kind: kind.to_string(), tags: tags.iter().map(|tag| tag.to_string()).collect() }
+    }
+
+    #[test]
+    fn accepts_kind_or_tag() {
+        let one = collector(Tags::One("paper".to_string()), None);
+        let many = collector(Tags::Many(vec!["coin".to_string(), "card".to_string()]), None);
+
+        assert!(one.accepts(&item("paper", &[])));
+        assert!(one.accepts(&item("card", &["paper"])));
+        assert!(!one.accepts(&item("card", &["red"])));
+        assert!(many.accepts(&item("card", &[])));
+        assert!(!many.accepts(&item("box", &["paper"])));
+    }
+
+    #[test]
+    fn fits_within_tolerance() {
+        let any = collector(Tags::One("card".to_string()), None);
+        let upright = collector(Tags::One("card".to_string()), Some(90.0));
+
+        assert!(any.fits(1.0));
+        assert!(upright.fits(90f32.to_radians()));
+        assert!(upright.fits(95f32.to_radians()));
+        assert!(!upright.fits(0.0));
+        assert!(!upright.fits(270f32.to_radians()));
+        // a full turn either way is the same orientation
+        assert!(upright.fits(450f32.to_radians()));
+        assert!(upright.fits((-275f32).to_radians()));
+    }
+
+    #[test]
+    fn fits_across_zero() {
+        let flat = collector(Tags::One("card".to_string()), Some(0.0));
+
+        assert!(flat.fits((-5f32).to_radians()));
+        assert!(flat.fits(355f32.to_radians()));
+        assert!(!flat.fits(20f32.to_radians()));
+    }
+}
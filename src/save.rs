@@ -0,0 +1,184 @@
+use hand::{Bindings, GhostMotion, Interpolation};
+use level::{HotReload, LevelCompleted};
+use serde::{Deserialize, Serialize};
+use state::{GameState, Progress};
+
+use super::*;
+
+/// Bumped whenever the layout of [`SaveData`] changes, older saves are ignored.
+const SAVE_VERSION: u32 = 1;
+
+// names of the stored files
+const SAVE: &str = "save";
+const BINDINGS: &str = "bindings";
+
+/// Everything kept between runs of the game.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct SaveData {
+    version: u32,
+    progress: Progress,
+    settings: Settings,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct Settings {
+    keep_ghosts_on_reload: bool,
+    ghost_interpolation: Interpolation,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            keep_ghosts_on_reload: HotReload::default().keep_ghosts,
+            ghost_interpolation: Interpolation::default(),
+        }
+    }
+}
+
+/// Reads the save into [`Progress`] and writes it back whenever a level is finished, and
+/// reads the input bindings before the hand is spawned.
+///
+/// Only the game registers this, so the `verify` binary never touches the player's save.
+pub fn register(app: &mut App) {
+    if let Some(save) = read_save() {
+        app
+            .insert_resource(save.progress)
+            .insert_resource(HotReload { keep_ghosts: save.settings.keep_ghosts_on_reload })
+            .insert_resource(GhostMotion(save.settings.ghost_interpolation));
+    }
+
+    if let Some(bindings) = read_bindings() {
+        app.insert_resource(Bindings::merged(bindings));
+    }
+
+    app
+        // after the level's result has been added to the progress
+        .add_systems(PostUpdate, write_save.run_if(on_event::<LevelCompleted>()))
+        .add_systems(OnExit(GameState::Controls), write_bindings);
+}
+
+fn read_bindings() -> Option<Bindings> {
+    let text = storage::read(BINDINGS)?;
+
+    match toml::from_str(&text) {
+        Ok(bindings) => Some(bindings),
+        Err(e) => {
+            error!("could not read the input bindings, using the defaults: {}", e);
+            None
+        }
+    }
+}
+
+fn write_bindings(bindings: Res<Bindings>) {
+    match toml::to_string(&*bindings) {
+        Ok(text) => storage::write(BINDINGS, &text),
+        Err(e) => error!("could not save the input bindings: {}", e),
+    }
+}
+
+fn read_save() -> Option<SaveData> {
+    let text = storage::read(SAVE)?;
+
+    let save: SaveData = match toml::from_str(&text) {
+        Ok(save) => save,
+        Err(e) => {
+            error!("could not read the save, starting over: {}", e);
+            return None;
+        }
+    };
+
+    if save.version != SAVE_VERSION {
+        warn!("ignoring save made for version {}, this game reads version {}", save.version, SAVE_VERSION);
+        return None;
+    }
+
+    Some(save)
+}
+
+fn write_save(progress: Res<Progress>, hot_reload: Res<HotReload>, ghost_motion: Res<GhostMotion>) {
+    let save = SaveData {
+        version: SAVE_VERSION,
+        progress: progress.clone(),
+        settings: Settings {
+            keep_ghosts_on_reload: hot_reload.keep_ghosts,
+            ghost_interpolation: ghost_motion.0,
+        },
+    };
+
+    match toml::to_string(&save) {
+        Ok(text) => storage::write(SAVE, &text),
+        Err(e) => error!("could not save the game: {}", e),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod storage {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    // in the platform's config directory, there is no need for a whole crate to find it
+    fn path(name: &str) -> Option<PathBuf> {
+        let config = std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+            .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+        Some(config.join("manos").join(format!("{}.toml", name)))
+    }
+
+    pub(super) fn read(name: &str) -> Option<String> {
+        let path = path(name)?;
+
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Some(text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                error!("could not read {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub(super) fn write(name: &str, text: &str) {
+        let Some(path) = path(name) else {
+            warn!("no config directory to save {} in", name);
+            return;
+        };
+
+        let result = path.parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| std::fs::write(&path, text));
+
+        match result {
+            Ok(()) => info!("saved {}", path.display()),
+            Err(e) => error!("could not save {}: {}", path.display(), e),
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod storage {
+    use super::*;
+
+    fn key(name: &str) -> String {
+        format!("manos-{}", name)
+    }
+
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    pub(super) fn read(name: &str) -> Option<String> {
+        local_storage()?.get_item(&key(name)).ok()?
+    }
+
+    pub(super) fn write(name: &str, text: &str) {
+        let saved = local_storage().is_some_and(|storage| storage.set_item(&key(name), text).is_ok());
+
+        if !saved {
+            error!("could not save {} to local storage", name);
+        }
+    }
+}
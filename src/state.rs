@@ -0,0 +1,152 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use avian2d::prelude::*;
+use editor::Editor;
+use hand::Playback;
+use level::{CampaignComplete, CurrentLevel, LevelCompleted, LevelManifest, ManifestHandle};
+use serde::{Deserialize, Serialize};
+use submit::LevelTime;
+
+use super::*;
+
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameState {
+    /// waiting for the level manifest
+    #[default]
+    Boot,
+    MainMenu,
+    LevelSelect,
+    /// remapping the hand's input bindings
+    Controls,
+    Playing,
+    Paused,
+    LevelComplete,
+}
+
+/// Levels the player has finished, a level unlocks once the one before it is finished.
+#[derive(Resource, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct Progress {
+    pub completed: HashSet<String>,
+    /// keyed by level id
+    pub best: HashMap<String, BestResult>,
+}
+
+/// The best results for a level, each one kept separately so they may come from different runs.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct BestResult {
+    /// seconds
+    pub time: f32,
+    pub ghosts: u32,
+}
+
+impl Progress {
+    pub fn complete(&mut self, id: &str, time: Duration, ghosts: u32) {
+        self.completed.insert(id.to_string());
+
+        let time = time.as_secs_f32();
+        self.best.entry(id.to_string())
+            .and_modify(|best| {
+                best.time = best.time.min(time);
+                best.ghosts = best.ghosts.min(ghosts);
+            })
+            .or_insert(BestResult { time, ghosts });
+    }
+
+    pub fn is_unlocked(&self, manifest: &LevelManifest, id: &str) -> bool {
+        match manifest.index_of(id) {
+            Some(0) => true,
+            Some(index) => self.completed.contains(id) || self.completed.contains(&manifest.levels[index - 1].id),
+            None => false,
+        }
+    }
+
+    /// The first level that hasn't been finished yet, or the last level once they all are.
+    pub fn next_unfinished<'a>(&self, manifest: &'a LevelManifest) -> Option<&'a str> {
+        manifest.levels.iter()
+            .find(|level| !self.completed.contains(&level.id))
+            .or(manifest.levels.last())
+            .map(|level| level.id.as_str())
+    }
+}
+
+/// Run condition for gameplay systems, true while playing and not editing the level.
+pub fn in_play(state: Res<State<GameState>>, editor: Res<Editor>) -> bool {
+    *state.get() == GameState::Playing && !editor.enabled
+}
+
+pub(super) fn register(app: &mut App) {
+    app
+        .init_state::<GameState>()
+        .enable_state_scoped_entities::<GameState>()
+        .init_resource::<Progress>()
+        .add_systems(Update, boot.run_if(in_state(GameState::Boot)))
+        .add_systems(Update, toggle_pause.run_if(in_state(GameState::Playing).or_else(in_state(GameState::Paused))))
+        .add_systems(Update, (complete_level, finish_campaign))
+        .add_systems(OnEnter(GameState::Playing), resume_physics)
+        .add_systems(OnExit(GameState::Playing), pause_physics);
+}
+
+fn boot(
+    manifest: Res<ManifestHandle>,
+    manifests: Res<Assets<LevelManifest>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if manifests.contains(&manifest.0) {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+fn toggle_pause(
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<State<GameState>>,
+    editor: Res<Editor>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !keys.just_pressed(KeyCode::Escape) || editor.enabled {
+        return;
+    }
+
+    match state.get() {
+        GameState::Playing => next_state.set(GameState::Paused),
+        GameState::Paused => next_state.set(GameState::Playing),
+        _ => {}
+    }
+}
+
+fn complete_level(
+    mut ev_completed: EventReader<LevelCompleted>,
+    current_level: Res<CurrentLevel>,
+    level_time: Res<LevelTime>,
+    ghosts: Query<(), With<Playback>>,
+    mut progress: ResMut<Progress>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if ev_completed.read().count() == 0 {
+        return;
+    }
+
+    if let Some(id) = &current_level.0 {
+        progress.complete(id, level_time.0.elapsed(), ghosts.iter().count() as u32);
+    }
+
+    next_state.set(GameState::LevelComplete);
+}
+
+fn finish_campaign(
+    mut ev_complete: EventReader<CampaignComplete>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if ev_complete.read().count() > 0 {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+fn resume_physics(mut physics_time: ResMut<Time<Physics>>) {
+    physics_time.unpause();
+}
+
+fn pause_physics(mut physics_time: ResMut<Time<Physics>>) {
+    physics_time.pause();
+}
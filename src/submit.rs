@@ -1,32 +1,269 @@
-use level::{CurrentLevel, LoadLevel};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use bevy::time::Stopwatch;
+use hand::{LoopLength, Playback};
+use level::LevelCompleted;
+use object::Item;
+use serde::{Deserialize, Serialize};
+use state::in_play;
 
 use super::*;
 
-#[derive(Resource)]
-pub struct Deliveries(pub i16);
+#[derive(Resource, Default)]
+pub struct Deliveries {
+    pub total: u32,
+    /// deliveries of each item kind and tag
+    pub items: HashMap<String, u32>,
+    // level time of every delivery, oldest first
+    pub times: Vec<Duration>,
+}
+
+impl Deliveries {
+    pub fn add(&mut self, item: &Item, at: Duration) {
+        self.total += 1;
+        for label in item.labels() {
+            *self.items.entry(label.clone()).or_default() += 1;
+        }
+        self.times.push(at);
+    }
+}
+
+/// Time spent in the current level.
+#[derive(Resource, Default)]
+pub struct LevelTime(pub Stopwatch);
+
+/// What the current level asks of the player, taken once it has been won.
+#[derive(Resource, Default)]
+pub struct LevelGoal(pub Option<WinCondition>);
+
+/// A level's goal as written in its `goal` key, e.g.
+/// `goal = { all = [{ deliver = { item = "card", count = 3 } }, "hands_off"] }`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum WinCondition {
+    /// `count` items with this kind or tag have been delivered
+    Deliver { item: String, count: u32 },
+    /// `count` items have been delivered within the last loop of the level
+    DeliverWithinLoop { count: u32 },
+    /// the level is won before `seconds` have passed, only allowed in an `all` next to the goals
+    /// it limits since on its own it's met from the start
+    TimeLimit { seconds: f32 },
+    /// the player's cursor is outside the window
+    HandsOff,
+    /// no ghost hands are playing back
+    NoGhosts,
+    All(Vec<WinCondition>),
+    Any(Vec<WinCondition>),
+}
+
+struct GoalState<'a> {
+    deliveries: &'a Deliveries,
+    elapsed: Duration,
+    loop_length: Duration,
+    ghosts: usize,
+    hands_off: bool,
+}
+
+impl WinCondition {
+    fn met(&self, state: &GoalState) -> bool {
+        match self {
+            WinCondition::Deliver { item, count } => {
+                state.deliveries.items.get(item).copied().unwrap_or(0) >= *count
+            }
+            WinCondition::DeliverWithinLoop { count } => {
+                let loop_start = state.elapsed.saturating_sub(state.loop_length);
+                let recent = state.deliveries.times.iter().filter(|time| **time >= loop_start).count();
+
+                recent as u32 >= *count
+            }
+            WinCondition::TimeLimit { seconds } => state.elapsed.as_secs_f32() <= *seconds,
+            WinCondition::HandsOff => state.hands_off,
+            WinCondition::NoGhosts => state.ghosts == 0,
+            WinCondition::All(conditions) => conditions.iter().all(|condition| condition.met(state)),
+            WinCondition::Any(conditions) => conditions.iter().any(|condition| condition.met(state)),
+        }
+    }
+
+    /// Why a time limit in this condition would let the level be won at once, `None` if every
+    /// one of them sits in an `all` with another goal.
+    pub fn time_limit_error(&self) -> Option<&'static str> {
+        self.misplaced_time_limit(false)
+    }
+
+    // `limiting` is set inside an `all` that has a goal besides time limits
+    fn misplaced_time_limit(&self, limiting: bool) -> Option<&'static str> {
+        match self {
+            WinCondition::TimeLimit { .. } if !limiting => {
+                Some("a time_limit is met from the start, put it in an `all` with the goals it limits")
+            }
+            WinCondition::All(conditions) => {
+                let limiting = conditions.iter().any(|condition| !matches!(condition, WinCondition::TimeLimit { .. }));
+
+                conditions.iter().find_map(|condition| condition.misplaced_time_limit(limiting))
+            }
+            WinCondition::Any(conditions) => {
+                conditions.iter().find_map(|condition| condition.misplaced_time_limit(false))
+            }
+            _ => None,
+        }
+    }
+
+    /// Every item kind or tag this condition counts deliveries of.
+    pub fn items(&self) -> Vec<&String> {
+        match self {
+            WinCondition::Deliver { item, .. } => vec![item],
+            WinCondition::All(conditions) | WinCondition::Any(conditions) => {
+                conditions.iter().flat_map(|condition| condition.items()).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
 
 pub(super) fn register(app: &mut App) {
     app
-        .insert_resource(Deliveries(0))
-        .add_systems(Update, submitting);
+        .init_resource::<Deliveries>()
+        .init_resource::<LevelTime>()
+        .init_resource::<LevelGoal>()
+        .add_systems(Update, (tick_level_time, submitting).chain().run_if(in_play));
+}
+
+fn tick_level_time(
+    mut level_time: ResMut<LevelTime>,
+    time: Res<Time>,
+) {
+    level_time.0.tick(time.delta());
 }
 
 fn submitting (
-    windows: Query<&mut Window>,
-    mut deliveries: ResMut<Deliveries>,
-    mut ev_level: EventWriter<LoadLevel>,
-    current_level: Res<CurrentLevel>,
+    windows: Query<&Window>,
+    ghosts: Query<(), With<Playback>>,
+    deliveries: Res<Deliveries>,
+    level_time: Res<LevelTime>,
+    loop_length: Res<LoopLength>,
+    mut goal: ResMut<LevelGoal>,
+    mut ev_level: EventWriter<LevelCompleted>,
 ) {
-    let window = windows.single();
+    let Some(condition) = &goal.0 else {
+        return;
+    };
 
-    if window.cursor_position().is_none() {
-        // submitting
-        if deliveries.0 >= 3 {
-            ev_level.send(LoadLevel(current_level.0+1));
-            deliveries.0 = 0;
+    // with no window (or the cursor outside of it) the player isn't touching anything
+    let hands_off = windows.get_single().map_or(true, |window| window.cursor_position().is_none());
+
+    let state = GoalState {
+        deliveries: &deliveries,
+        elapsed: level_time.0.elapsed(),
+        loop_length: loop_length.0,
+        ghosts: ghosts.iter().count(),
+        hands_off,
+    };
+
+    if condition.met(&state) {
+        ev_level.send(LevelCompleted);
+        goal.0 = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use WinCondition::{All, Any, Deliver, DeliverWithinLoop, HandsOff, NoGhosts, TimeLimit};
+
+    fn deliver(item: &str, count: u32) -> WinCondition {
+        Deliver { item: item.to_string(), count }
+    }
+
+    fn time_limit(seconds: f32) -> WinCondition {
+        TimeLimit { seconds }
+    }
+
+    // cards tagged paper delivered at each of `times`, in seconds
+    fn cards(times: &[f32]) -> Deliveries {
+        let card = Item { kind: "card".to_string(), tags: vec!["paper".to_string()] };
+
+        let mut deliveries = Deliveries::default();
+        for time in times {
+            deliveries.add(&card, Duration::from_secs_f32(*time));
+        }
+        deliveries
+    }
+
+    fn at(deliveries: &Deliveries, elapsed: f32) -> GoalState {
+        GoalState {
+            deliveries,
+            elapsed: Duration::from_secs_f32(elapsed),
+            loop_length: Duration::from_secs(3),
+            ghosts: 0,
+            hands_off: false,
         }
-    } else {
-        // not submitting
-        deliveries.0 = 0;
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn deliver_counts_kinds_and_tags() {
+        let deliveries = cards(&[1.0, 2.0]);
+
+        assert_eq!(deliveries.total, 2);
+        assert!(deliver("card", 2).met(&at(&deliveries, 5.0)));
+        assert!(deliver("paper", 2).met(&at(&deliveries, 5.0)));
+        assert!(!deliver("card", 3).met(&at(&deliveries, 5.0)));
+        assert!(!deliver("coin", 1).met(&at(&deliveries, 5.0)));
+    }
+
+    #[test]
+    fn deliver_within_loop_forgets_old_deliveries() {
+        let deliveries = cards(&[1.0, 4.5, 6.0]);
+        let condition = DeliverWithinLoop { count: 2 };
+
+        // a loop is 3 seconds, at 7 the first card is too old
+        assert!(condition.met(&at(&deliveries, 7.0)));
+        assert!(!condition.met(&at(&deliveries, 8.0)));
+
+        let longer = GoalState { loop_length: Duration::from_secs(10), ..at(&deliveries, 12.0) };
+        assert!(condition.met(&longer));
+    }
+
+    #[test]
+    fn time_limit_runs_out() {
+        let deliveries = cards(&[1.0]);
+        let condition = All(vec![deliver("card", 1), time_limit(10.0)]);
+
+        assert!(condition.met(&at(&deliveries, 9.5)));
+        assert!(!condition.met(&at(&deliveries, 10.5)));
+    }
+
+    #[test]
+    fn hands_off_and_no_ghosts() {
+        let deliveries = Deliveries::default();
+        let mut state = at(&deliveries, 0.0);
+
+        assert!(!HandsOff.met(&state));
+        assert!(NoGhosts.met(&state));
+
+        state.hands_off = true;
+        state.ghosts = 2;
+
+        assert!(HandsOff.met(&state));
+        assert!(!NoGhosts.met(&state));
+        assert!(!All(vec![HandsOff, NoGhosts]).met(&state));
+        assert!(Any(vec![HandsOff, NoGhosts]).met(&state));
+    }
+
+    #[test]
+    fn time_limit_needs_a_goal_beside_it() {
+        assert!(time_limit(10.0).time_limit_error().is_some());
+        assert!(All(vec![time_limit(10.0), time_limit(20.0)]).time_limit_error().is_some());
+        assert!(Any(vec![time_limit(10.0), HandsOff]).time_limit_error().is_some());
+
+        assert!(All(vec![time_limit(10.0), HandsOff]).time_limit_error().is_none());
+        assert!(Any(vec![All(vec![HandsOff, time_limit(10.0)]), NoGhosts]).time_limit_error().is_none());
+    }
+
+    #[test]
+    fn items_are_collected_from_nested_goals() {
+        let condition = Any(vec![deliver("card", 1), All(vec![deliver("paper", 2), HandsOff])]);
+
+        assert_eq!(condition.items(), ["card", "paper"]);
+    }
+}